@@ -0,0 +1,63 @@
+/*!
+The core auth operations as a trait, so code depending on them can be tested against a mock.
+
+[`AuthClient`] implements [`AuthApi`] by calling its inherent methods. Take a `&dyn AuthApi` or
+`Box<dyn AuthApi>` in your own code and pass a mock in tests:
+
+```rust
+use supabase_auth::api::{async_trait, AuthApi};
+
+struct MockAuth;
+
+#[async_trait]
+impl AuthApi for MockAuth {
+    async fn get_user(&self, bearer_token: &str) -> Result<User, Error> {
+        Ok(test_user())
+    }
+
+    // ...
+}
+```
+*/
+
+pub use async_trait::async_trait;
+
+use crate::{
+    error::Error,
+    models::{AuthClient, LogoutScope, Session, User},
+};
+
+/// The core operations of an [`AuthClient`]
+#[async_trait]
+pub trait AuthApi: Send + Sync {
+    /// See [`AuthClient::login_with_email`]
+    async fn login_with_email(&self, email: &str, password: &str) -> Result<Session, Error>;
+
+    /// See [`AuthClient::get_user`]
+    async fn get_user(&self, bearer_token: &str) -> Result<User, Error>;
+
+    /// See [`AuthClient::refresh_session`]
+    async fn refresh_session(&self, refresh_token: &str) -> Result<Session, Error>;
+
+    /// See [`AuthClient::logout`]
+    async fn logout(&self, scope: Option<LogoutScope>, bearer_token: &str) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl AuthApi for AuthClient {
+    async fn login_with_email(&self, email: &str, password: &str) -> Result<Session, Error> {
+        AuthClient::login_with_email(self, email, password).await
+    }
+
+    async fn get_user(&self, bearer_token: &str) -> Result<User, Error> {
+        AuthClient::get_user(self, bearer_token).await
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<Session, Error> {
+        AuthClient::refresh_session(self, refresh_token).await
+    }
+
+    async fn logout(&self, scope: Option<LogoutScope>, bearer_token: &str) -> Result<(), Error> {
+        AuthClient::logout(self, scope, bearer_token).await
+    }
+}
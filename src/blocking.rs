@@ -0,0 +1,1824 @@
+/*!
+A blocking (synchronous) Supabase Auth client, built on `reqwest::blocking`.
+
+Enable the `blocking` feature to use it. The blocking [`AuthClient`] has the same methods as the
+async `AuthClient`, minus `async`, and uses the same models and errors. Programs
+that only need the blocking client can turn off the default `async` feature, which drops the async
+client along with the crate's own tokio, futures, and async-trait dependencies:
+
+```toml
+[dependencies]
+supabase-auth = { version = "0.10", default-features = false, features = ["rustls", "blocking"] }
+```
+
+```rust
+use supabase_auth::blocking::AuthClient;
+
+let auth_client = AuthClient::new(project_url, api_key, jwt_secret)?;
+
+let session = auth_client.login_with_email(demo_email, demo_password)?;
+```
+
+`session_watch`, `start_auto_refresh`, and `admin_users_stream` need an async runtime, so they
+only exist on the async client. [`get_session`](AuthClient::get_session) refreshes the stored
+session when it is about to expire.
+
+# Panics
+Like `reqwest::blocking`, the client must not be used from within an async runtime, e.g. a
+`#[tokio::main]` function: it panics when it would block there. Use the async client instead, or
+move the call to a blocking thread with `tokio::task::spawn_blocking`.
+*/
+
+use core::fmt;
+use std::{
+    env,
+    sync::{atomic::AtomicI64, Arc, Mutex, PoisonError, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::DecodingKey;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, StatusCode, Url,
+};
+use serde::Serialize;
+use serde_json::{from_str, Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::{Error, SupabaseHTTPError},
+    models::{
+        AdminUserAttributes, AuthServerHealth, AuthServerSettings, AuthenticatorAssuranceLevel,
+        Channel, Claims, CreateSsoProviderParams, CreateSsoProviderPayload, EmailChangeResponse,
+        EmailOtpType, EmailSignUpConfirmation, EmailSignUpResult, ExchangeCodeForSessionPayload,
+        Factor, GotrueMetaSecurity, IdTokenCredentials, InviteParams, JwtValidation,
+        ListUsersParams, ListUsersResponse, LoginAnonymouslyOptions, LoginAnonymouslyPayload,
+        LoginEmailOtpParams, LoginMobileOtpParams, LoginWithEmailAndPasswordPayload,
+        LoginWithEmailOtpPayload, LoginWithOAuthOptions, LoginWithPasswordOptions,
+        LoginWithPhoneAndPasswordPayload, LoginWithSSO, LoginWithSSOPayload, LogoutScope,
+        OAuthResponse, OTPResponse, OtpTarget, OtpType, PasswordPolicy, Provider,
+        RefreshSessionPayload, RequestMagicLinkPayload, ResendParams, ResetPasswordForEmailPayload,
+        ResetPasswordOptions, SendSMSOtpPayload, Session, SignUpWithEmailAndPasswordPayload,
+        SignUpWithPasswordOptions, SignUpWithPhoneAndPasswordPayload, SsoProvider,
+        SsoProvidersResponse, SubjectClaims, UpdateUserResult, UpdatedUser, User, VerifyOtpParams,
+        AUTH_V1, DEFAULT_REFRESH_MARGIN, DEFAULT_SETTINGS_TTL,
+    },
+    shared::{
+        code_from_callback_url, decode_jwt_unverified, decode_verified, effective_refresh_margin,
+        format_ban_duration, is_invalid_refresh_token, oauth_url, parse_success_body,
+        password_policy, retry_after, session_from_cookies, session_to_set_cookie_headers,
+        unix_time, user_from_claims, verify_oauth_state, warn_on_long_refresh_margin,
+        with_redirect_to,
+    },
+};
+
+/// A blocking Supabase Auth client
+#[derive(Clone)]
+pub struct AuthClient {
+    client: Client,
+    project_url: String,
+    api_key: String,
+    jwt_secret: String,
+    /// Session of the signed in user, stored by the login, sign up, and refresh methods
+    session: Arc<RwLock<Option<Session>>>,
+    /// Timeout applied to each request, set with [`AuthClient::with_timeout`]
+    request_timeout: Option<Duration>,
+    refresh_margin: Duration,
+    /// Settings returned by the last [`AuthClient::get_settings`] call and when they were fetched
+    settings_cache: Arc<RwLock<Option<(Instant, AuthServerSettings)>>>,
+    settings_ttl: Duration,
+    auth_path: String,
+    clock: Arc<dyn Clock>,
+    /// Held while the stored session is refreshed, so concurrent callers share a single refresh
+    refresh_lock: Arc<Mutex<()>>,
+    persist_session: bool,
+    /// The session lifetime the refresh margin was last found too long for
+    margin_warned_for: Arc<AtomicI64>,
+    retry_on_401: bool,
+}
+
+/// Builder for a blocking [`AuthClient`], created with [`AuthClient::builder`]
+pub struct AuthClientBuilder {
+    project_url: String,
+    api_key: String,
+    jwt_secret: String,
+    http_client: Option<Client>,
+    refresh_margin: Duration,
+    settings_ttl: Duration,
+    auth_path: String,
+    clock: Arc<dyn Clock>,
+    persist_session: bool,
+    retry_on_401: bool,
+}
+
+/// A response whose body was read
+struct Reply {
+    status: StatusCode,
+    url: Url,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl AuthClient {
+    /// Create a new blocking Auth Client
+    /// # Example
+    /// ```
+    /// let auth_client = AuthClient::new(project_url, api_key, jwt_secret).unwrap();
+    /// ```
+    pub fn new(
+        project_url: impl Into<String>,
+        api_key: impl Into<String>,
+        jwt_secret: impl Into<String>,
+    ) -> Result<Self, Error> {
+        AuthClient::builder(project_url, api_key, jwt_secret).build()
+    }
+
+    /// Create a builder to configure a new blocking Auth Client
+    /// # Example
+    /// ```
+    /// let auth_client = AuthClient::builder(project_url, api_key, jwt_secret)
+    ///     .http_client(reqwest::blocking::Client::new())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(
+        project_url: impl Into<String>,
+        api_key: impl Into<String>,
+        jwt_secret: impl Into<String>,
+    ) -> AuthClientBuilder {
+        AuthClientBuilder {
+            project_url: project_url.into(),
+            api_key: api_key.into(),
+            jwt_secret: jwt_secret.into(),
+            http_client: None,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            settings_ttl: DEFAULT_SETTINGS_TTL,
+            auth_path: AUTH_V1.to_string(),
+            clock: Arc::new(SystemClock),
+            persist_session: true,
+            retry_on_401: false,
+        }
+    }
+
+    /// Create a new blocking Auth Client from the `SUPABASE_URL`, `SUPABASE_API_KEY` and
+    /// `SUPABASE_JWT_SECRET` environment variables
+    pub fn new_from_env() -> Result<AuthClient, Error> {
+        let project_url = env::var("SUPABASE_URL")?;
+        let api_key = env::var("SUPABASE_API_KEY")?;
+        let jwt_secret = env::var("SUPABASE_JWT_SECRET")?;
+
+        AuthClient::builder(project_url, api_key, jwt_secret).build()
+    }
+
+    /// Sign in a user with an email and password
+    ///
+    /// Returns [`Error::EmailNotConfirmed`] if the user hasn't confirmed their email yet.
+    pub fn login_with_email(&self, email: &str, password: &str) -> Result<Session, Error> {
+        self.login_with_email_with_options(email, password, None)
+    }
+
+    /// Sign in a user with an email and password, passing a captcha token
+    pub fn login_with_email_with_options(
+        &self,
+        email: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+    ) -> Result<Session, Error> {
+        self.login_with_email_with_headers(email, password, options, HeaderMap::new())
+    }
+
+    /// Sign in a user with an email and password, sending `extra_headers` with this request
+    /// only. The extra headers can't replace the `apikey` or `Content-Type` headers.
+    pub fn login_with_email_with_headers(
+        &self,
+        email: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+        extra_headers: HeaderMap,
+    ) -> Result<Session, Error> {
+        let payload = LoginWithEmailAndPasswordPayload {
+            email,
+            password,
+            gotrue_meta_security: options.map(GotrueMetaSecurity::from),
+        };
+
+        match self.token_grant_with_headers(
+            "password",
+            serde_json::to_value(&payload)?,
+            extra_headers,
+        ) {
+            Err(Error::AuthError {
+                code: Some(code), ..
+            }) if code == "email_not_confirmed" => Err(Error::EmailNotConfirmed),
+            result => result,
+        }
+    }
+
+    /// Sign in a user with phone number and password
+    pub fn login_with_phone(&self, phone: &str, password: &str) -> Result<Session, Error> {
+        self.login_with_phone_with_options(phone, password, None)
+    }
+
+    /// Sign in a user with phone number and password, passing a captcha token
+    pub fn login_with_phone_with_options(
+        &self,
+        phone: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+    ) -> Result<Session, Error> {
+        let payload = LoginWithPhoneAndPasswordPayload {
+            phone,
+            password,
+            gotrue_meta_security: options.map(GotrueMetaSecurity::from),
+        };
+
+        self.token_grant("password", serde_json::to_value(&payload)?)
+    }
+
+    /// Sign up a new user with an email and password
+    pub fn sign_up_with_email_and_password(
+        &self,
+        email: &str,
+        password: &str,
+        options: Option<SignUpWithPasswordOptions>,
+    ) -> Result<EmailSignUpResult, Error> {
+        let redirect_to = options
+            .as_ref()
+            .and_then(|o| o.email_redirect_to.as_deref().map(str::to_owned));
+
+        let payload = SignUpWithEmailAndPasswordPayload {
+            email,
+            password,
+            options,
+        };
+
+        let request = self
+            .json_request(Method::POST, "/signup", &payload)?
+            .query(&[("redirect_to", redirect_to.as_deref())]);
+
+        let reply = self.send(request)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            self.store_session(&session);
+            return Ok(EmailSignUpResult::SessionResult(session));
+        }
+
+        if let Ok(result) = from_str::<EmailSignUpConfirmation>(&reply.body) {
+            return Ok(EmailSignUpResult::ConfirmationResult(result));
+        }
+
+        Err(naming_redirect_to(
+            reply.into_error(Method::POST),
+            redirect_to.as_deref(),
+        ))
+    }
+
+    /// Sign up a new user with a phone number and password
+    pub fn sign_up_with_phone_and_password(
+        &self,
+        phone: &str,
+        password: &str,
+        options: Option<SignUpWithPasswordOptions>,
+    ) -> Result<Session, Error> {
+        let redirect_to = options
+            .as_ref()
+            .and_then(|o| o.email_redirect_to.as_deref().map(str::to_owned));
+
+        let payload = SignUpWithPhoneAndPasswordPayload {
+            phone,
+            password,
+            options,
+        };
+
+        let request = self
+            .json_request(Method::POST, "/signup", &payload)?
+            .query(&[("redirect_to", redirect_to.as_deref())]);
+
+        let reply = self.send(request)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        Err(naming_redirect_to(
+            reply.into_error(Method::POST),
+            redirect_to.as_deref(),
+        ))
+    }
+
+    /// Sign in a new user anonymously. Every call creates a new anonymous user.
+    ///
+    /// This method requires anonymous sign in to be enabled in your dashboard.
+    pub fn login_anonymously(
+        &self,
+        options: Option<LoginAnonymouslyOptions>,
+    ) -> Result<Session, Error> {
+        let payload = LoginAnonymouslyPayload { options };
+
+        let reply = self.send(self.json_request(Method::POST, "/signup", &payload)?)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Sends a login email containing a magic link
+    pub fn send_login_email_with_magic_link(&self, email: &str) -> Result<(), Error> {
+        let payload = RequestMagicLinkPayload { email };
+
+        let reply = self.send(self.json_request(Method::POST, "/magiclink", &payload)?)?;
+
+        reply.check_rate_limit()?;
+
+        if reply.status.is_success() {
+            return Ok(());
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Send a Login OTP via SMS
+    pub fn send_sms_with_otp(&self, phone: &str) -> Result<OTPResponse, Error> {
+        self.send_phone_otp(phone, Channel::Sms)
+    }
+
+    /// Send a Login OTP to a phone number over `channel`, SMS or WhatsApp
+    pub fn send_phone_otp(&self, phone: &str, channel: Channel) -> Result<OTPResponse, Error> {
+        let options = LoginMobileOtpParams {
+            channel: Some(channel),
+            ..Default::default()
+        };
+
+        self.send_sms_with_otp_with_options(phone, Some(options))
+    }
+
+    /// Send a Login OTP to a phone number with options
+    pub fn send_sms_with_otp_with_options(
+        &self,
+        phone: &str,
+        options: Option<LoginMobileOtpParams>,
+    ) -> Result<OTPResponse, Error> {
+        let gotrue_meta_security = options
+            .as_ref()
+            .and_then(|options| options.captcha_token.clone())
+            .map(|captcha_token| GotrueMetaSecurity {
+                captcha_token: Some(captcha_token),
+            });
+
+        let payload = SendSMSOtpPayload {
+            phone,
+            options,
+            gotrue_meta_security,
+        };
+
+        let reply = self.send(self.json_request(Method::POST, "/otp", &payload)?)?;
+
+        reply.check_rate_limit()?;
+
+        if reply.status.is_success() {
+            return parse_success_body(&reply.body);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Send a Login OTP via email
+    pub fn send_email_with_otp(
+        &self,
+        email: &str,
+        options: Option<LoginEmailOtpParams>,
+    ) -> Result<OTPResponse, Error> {
+        let gotrue_meta_security = options
+            .as_ref()
+            .and_then(|options| options.captcha_token.clone())
+            .map(|captcha_token| GotrueMetaSecurity {
+                captcha_token: Some(captcha_token),
+            });
+
+        let payload = LoginWithEmailOtpPayload {
+            email,
+            options,
+            gotrue_meta_security,
+        };
+
+        let reply = self.send(self.json_request(Method::POST, "/otp", &payload)?)?;
+
+        reply.check_rate_limit()?;
+
+        if reply.status.is_success() {
+            return parse_success_body(&reply.body);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Send a Login OTP to an email address or phone number
+    pub fn send_otp(&self, target: OtpTarget) -> Result<OTPResponse, Error> {
+        match target {
+            OtpTarget::Email { email, options } => self.send_email_with_otp(&email, options),
+            OtpTarget::Phone { phone, options } => {
+                self.send_sms_with_otp_with_options(&phone, options)
+            }
+        }
+    }
+
+    /// Sign in a user using an OAuth provider, returning the URL to send the user to
+    pub fn login_with_oauth(
+        &self,
+        provider: Provider,
+        options: Option<LoginWithOAuthOptions>,
+    ) -> Result<OAuthResponse, Error> {
+        oauth_url(&self.project_url, &self.auth_path, provider, options)
+    }
+
+    /// Sign up a user using an OAuth provider, the same as
+    /// [`login_with_oauth`](AuthClient::login_with_oauth)
+    pub fn sign_up_with_oauth(
+        &self,
+        provider: Provider,
+        options: Option<LoginWithOAuthOptions>,
+    ) -> Result<OAuthResponse, Error> {
+        self.login_with_oauth(provider, options)
+    }
+
+    /// Verify that the `state` returned to your OAuth callback matches the one generated by
+    /// [`login_with_oauth`](AuthClient::login_with_oauth)
+    pub fn verify_oauth_state(
+        &self,
+        callback_url: &str,
+        expected_state: &str,
+    ) -> Result<(), Error> {
+        verify_oauth_state(callback_url, expected_state)
+    }
+
+    /// Return the signed in User
+    pub fn get_user(&self, bearer_token: &str) -> Result<User, Error> {
+        let request = with_bearer(self.request(Method::GET, "/user")?, bearer_token)?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        if let Ok(user) = from_str(&reply.body) {
+            return Ok(user);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Like [`get_user`](AuthClient::get_user), but returns the untyped JSON body
+    pub fn get_user_raw(&self, bearer_token: &str) -> Result<Value, Error> {
+        let request = with_bearer(self.request(Method::GET, "/user")?, bearer_token)?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        if reply.status.is_success() {
+            return Ok(from_str(&reply.body)?);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Like [`get_user`](AuthClient::get_user), but returns `Ok(None)` when the server rejects
+    /// the token with 401 or 403
+    pub fn try_get_user(&self, bearer_token: &str) -> Result<Option<User>, Error> {
+        match self.get_user(bearer_token) {
+            Ok(user) => Ok(Some(user)),
+            Err(Error::AuthError { status, .. } | Error::UnexpectedResponse { status, .. })
+                if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Update the user, such as changing email or password. Each field (email, password, and data) is optional
+    pub fn update_user(
+        &self,
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<User, Error> {
+        self.update_user_with_session(updated_user, bearer_token)
+            .map(|result| result.user)
+    }
+
+    /// Like [`update_user`](AuthClient::update_user), but also returns the new session if
+    /// GoTrue rotated the tokens. A returned session is stored if `bearer_token` is the stored
+    /// session's access token.
+    pub fn update_user_with_session(
+        &self,
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<UpdateUserResult, Error> {
+        // Checked before sending, a retry on 401 replaces the stored session's token
+        let owns_stored_session = self
+            .session()
+            .is_some_and(|session| session.access_token == bearer_token);
+
+        let request = with_bearer(
+            self.json_request(Method::PUT, "/user", &updated_user)?,
+            bearer_token,
+        )?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            if owns_stored_session {
+                self.store_session(&session);
+            }
+            return Ok(UpdateUserResult {
+                user: session.user.clone(),
+                new_session: Some(session),
+            });
+        }
+
+        if let Ok(user) = from_str(&reply.body) {
+            return Ok(UpdateUserResult {
+                user,
+                new_session: None,
+            });
+        }
+
+        Err(reply.into_error(Method::PUT))
+    }
+
+    /// Like [`update_user`](AuthClient::update_user), but returns the untyped JSON body
+    pub fn update_user_raw(
+        &self,
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<Value, Error> {
+        let request = with_bearer(
+            self.json_request(Method::PUT, "/user", &updated_user)?,
+            bearer_token,
+        )?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        if reply.status.is_success() {
+            return Ok(from_str(&reply.body)?);
+        }
+
+        Err(reply.into_error(Method::PUT))
+    }
+
+    /// Allows signing in with an OIDC ID token. The authentication provider used should be enabled and configured.
+    pub fn login_with_id_token(&self, credentials: IdTokenCredentials) -> Result<Session, Error> {
+        self.token_grant("id_token", serde_json::to_value(&credentials)?)
+    }
+
+    /// Sends an invite link to an email address. Requires admin permissions to issue invites.
+    pub fn invite_user_by_email(
+        &self,
+        email: &str,
+        data: Option<Value>,
+        bearer_token: &str,
+    ) -> Result<User, Error> {
+        let invite_payload = InviteParams {
+            email: email.into(),
+            data,
+        };
+
+        let request = with_bearer(
+            self.json_request(Method::POST, "/invite", &invite_payload)?,
+            bearer_token,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(user) = from_str(&reply.body) {
+            return Ok(user);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Create a user directly, skipping the signup flow. Requires the service role key.
+    pub fn admin_create_user(
+        &self,
+        params: AdminUserAttributes,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let request = with_bearer(
+            self.json_request(Method::POST, "/admin/users", &params)?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(user) = from_str(&reply.body) {
+            return Ok(user);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Update any user by their id. Requires the service role key.
+    pub fn admin_update_user_by_id(
+        &self,
+        user_id: &str,
+        params: AdminUserAttributes,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let request = with_bearer(
+            self.json_request(Method::PUT, &format!("/admin/users/{}", user_id), &params)?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(user) = from_str(&reply.body) {
+            return Ok(user);
+        }
+
+        Err(reply.into_error(Method::PUT))
+    }
+
+    /// Set a single key of a user's `app_metadata`. Requires the service role key.
+    pub fn admin_set_app_metadata(
+        &self,
+        user_id: &str,
+        key: &str,
+        value: Value,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            app_metadata: Some(Value::Object(Map::from_iter([(key.to_owned(), value)]))),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+    }
+
+    /// Ban a user for the given duration. Requires the service role key.
+    pub fn admin_ban_user(
+        &self,
+        user_id: &str,
+        duration: Duration,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            ban_duration: Some(format_ban_duration(duration)),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+    }
+
+    /// Lift a user's ban. Requires the service role key.
+    pub fn admin_unban_user(&self, user_id: &str, service_role_key: &str) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            ban_duration: Some("none".to_string()),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+    }
+
+    /// Delete a user. Requires the service role key.
+    pub fn admin_delete_user(&self, user_id: &str, service_role_key: &str) -> Result<(), Error> {
+        let request = with_bearer(
+            self.request(Method::DELETE, &format!("/admin/users/{}", user_id))?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if reply.status.is_success() {
+            return Ok(());
+        }
+
+        Err(reply.into_error(Method::DELETE))
+    }
+
+    /// Delete many users, running up to `concurrency` deletions at once on their own threads.
+    /// Requires the service role key.
+    ///
+    /// `concurrency` is capped at 10 to stay clear of rate limits. Every id is attempted, the
+    /// result of each is returned in the order the deletions finished.
+    pub fn admin_delete_users(
+        &self,
+        ids: &[String],
+        concurrency: usize,
+        service_role_key: &str,
+    ) -> Vec<(String, Result<(), Error>)> {
+        const MAX_CONCURRENCY: usize = 10;
+
+        let pending = Mutex::new(ids.iter());
+        let results = Mutex::new(Vec::with_capacity(ids.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, MAX_CONCURRENCY).min(ids.len()) {
+                scope.spawn(|| loop {
+                    let next = pending
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .next();
+
+                    let Some(id) = next else {
+                        break;
+                    };
+
+                    let result = self.admin_delete_user(id, service_role_key);
+
+                    results
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((id.clone(), result));
+                });
+            }
+        });
+
+        results.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// List the users of the project, one page at a time. Requires the service role key.
+    pub fn admin_list_users(
+        &self,
+        params: Option<ListUsersParams>,
+        service_role_key: &str,
+    ) -> Result<ListUsersResponse, Error> {
+        let request = with_bearer(
+            self.request(Method::GET, "/admin/users")?
+                .query(&params.unwrap_or_default()),
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(users) = from_str(&reply.body) {
+            return Ok(users);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// List the SSO providers of the project. Requires the service role key.
+    pub fn admin_list_sso_providers(
+        &self,
+        service_role_key: &str,
+    ) -> Result<Vec<SsoProvider>, Error> {
+        let request = with_bearer(
+            self.request(Method::GET, "/admin/sso/providers")?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(providers) = from_str::<SsoProvidersResponse>(&reply.body) {
+            return Ok(providers.items);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Get an SSO provider by its id or resource id. Requires the service role key.
+    pub fn admin_get_sso_provider(
+        &self,
+        provider_id: &str,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
+        let request = with_bearer(
+            self.request(
+                Method::GET,
+                &format!("/admin/sso/providers/{}", provider_id),
+            )?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(provider) = from_str(&reply.body) {
+            return Ok(provider);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Add a SAML SSO provider to the project. Requires the service role key.
+    pub fn admin_create_sso_provider(
+        &self,
+        params: CreateSsoProviderParams,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
+        let payload = CreateSsoProviderPayload {
+            provider_type: "saml",
+            params,
+        };
+
+        let request = with_bearer(
+            self.json_request(Method::POST, "/admin/sso/providers", &payload)?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(provider) = from_str(&reply.body) {
+            return Ok(provider);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Remove an SSO provider from the project, returning the removed provider.
+    /// Requires the service role key.
+    pub fn admin_delete_sso_provider(
+        &self,
+        provider_id: &str,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
+        let request = with_bearer(
+            self.request(
+                Method::DELETE,
+                &format!("/admin/sso/providers/{}", provider_id),
+            )?,
+            service_role_key,
+        )?;
+
+        let reply = self.send(request)?;
+
+        if let Ok(provider) = from_str(&reply.body) {
+            return Ok(provider);
+        }
+
+        Err(reply.into_error(Method::DELETE))
+    }
+
+    /// Find a user by their email address, ignoring case. Returns `None` if no user matches.
+    /// Requires the service role key.
+    pub fn admin_get_user_by_email(
+        &self,
+        email: &str,
+        service_role_key: &str,
+    ) -> Result<Option<User>, Error> {
+        const PER_PAGE: u32 = 100;
+
+        let mut page = 1;
+
+        loop {
+            let params = ListUsersParams {
+                page: Some(page),
+                per_page: Some(PER_PAGE),
+                filter: Some(email.to_string()),
+            };
+
+            let users = self.admin_list_users(Some(params), service_role_key)?.users;
+
+            let count = users.len();
+
+            if let Some(user) = users
+                .into_iter()
+                .find(|user| user.email.eq_ignore_ascii_case(email))
+            {
+                return Ok(Some(user));
+            }
+
+            if count < PER_PAGE as usize {
+                return Ok(None);
+            }
+
+            page += 1;
+        }
+    }
+
+    /// Verify the OTP sent to the user, storing the returned session like the login methods
+    pub fn verify_otp(&self, params: VerifyOtpParams) -> Result<Session, Error> {
+        let reply = self.send(self.json_request(Method::POST, "/verify", &params)?)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Confirm a new email address with the token sent by [`update_user`](AuthClient::update_user).
+    /// The first of two required confirmations returns [`Error::EmailChangePending`].
+    pub fn verify_email_change(&self, email: &str, token: &str) -> Result<Session, Error> {
+        let params = VerifyOtpParams::email(email, token, EmailOtpType::EmailChange);
+
+        let reply = self.send(self.json_request(Method::POST, "/verify", &params)?)?;
+
+        if reply.status.is_success() {
+            match from_str::<EmailChangeResponse>(&reply.body) {
+                Ok(EmailChangeResponse::Session(session)) => {
+                    self.store_session(&session);
+                    return Ok(session);
+                }
+                Ok(EmailChangeResponse::Pending { .. }) => return Err(Error::EmailChangePending),
+                Err(_) => return Err(reply.into_unexpected(Method::POST)),
+            }
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Check the Health Status of the Auth Server
+    pub fn get_health(&self) -> Result<AuthServerHealth, Error> {
+        let reply = self.send(self.request(Method::GET, "/health")?)?;
+
+        if let Ok(health) = from_str::<AuthServerHealth>(&reply.body) {
+            return Ok(health);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Check the Health Status of the Auth Server, failing once `timeout` passes
+    pub fn get_health_with(&self, timeout: Duration) -> Result<AuthServerHealth, Error> {
+        self.with_timeout(timeout).get_health()
+    }
+
+    /// Returns true if the Auth Server answers its health check within `timeout`
+    pub fn is_healthy(&self, timeout: Duration) -> bool {
+        self.get_health_with(timeout).is_ok()
+    }
+
+    /// Open a connection to the Auth Server ahead of the first real request. Best effort, errors
+    /// can be ignored.
+    pub fn warmup(&self) -> Result<(), Error> {
+        self.get_health().map(|_| ())
+    }
+
+    /// Retrieve the public settings of the server, reusing the last result within the TTL set
+    /// with [`AuthClientBuilder::settings_ttl`]
+    pub fn get_settings(&self) -> Result<AuthServerSettings, Error> {
+        if let Ok(cache) = self.settings_cache.read() {
+            if let Some((fetched_at, settings)) = cache.as_ref() {
+                if fetched_at.elapsed() < self.settings_ttl {
+                    return Ok(settings.clone());
+                }
+            }
+        }
+
+        self.get_settings_uncached()
+    }
+
+    /// Whether new email signups have to confirm their email before they get a session. Read
+    /// from the cached [`get_settings`](AuthClient::get_settings).
+    pub fn email_confirmation_required(&self) -> Result<bool, Error> {
+        let settings = self.get_settings()?;
+
+        Ok(!settings.disable_signup && !settings.mailer_autoconfirm)
+    }
+
+    /// The password requirements of the server. Read from the cached
+    /// [`get_settings`](AuthClient::get_settings).
+    pub fn password_policy(&self) -> Result<PasswordPolicy, Error> {
+        let settings = self.get_settings()?;
+
+        Ok(password_policy(&settings))
+    }
+
+    /// Retrieve the public settings of the server, bypassing and refreshing the cache
+    pub fn get_settings_uncached(&self) -> Result<AuthServerSettings, Error> {
+        let reply = self.send(self.request(Method::GET, "/settings")?)?;
+
+        if let Ok(settings) = from_str::<AuthServerSettings>(&reply.body) {
+            if let Ok(mut cache) = self.settings_cache.write() {
+                *cache = Some((Instant::now(), settings.clone()));
+            }
+
+            return Ok(settings);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Retrieve the public settings of the server as untyped JSON. These are never cached.
+    pub fn get_settings_raw(&self) -> Result<Value, Error> {
+        let reply = self.send(self.request(Method::GET, "/settings")?)?;
+
+        if reply.status.is_success() {
+            return Ok(from_str(&reply.body)?);
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Request a session from the `/token` endpoint with any `grant_type`. `body` is sent as is
+    /// and the returned session is stored like the login methods do.
+    pub fn token_grant(&self, grant_type: &str, body: Value) -> Result<Session, Error> {
+        self.token_grant_with_headers(grant_type, body, HeaderMap::new())
+    }
+
+    /// [`token_grant`](AuthClient::token_grant), sending `extra_headers` along with the
+    /// client's own headers, which take precedence
+    fn token_grant_with_headers(
+        &self,
+        grant_type: &str,
+        body: Value,
+        mut extra_headers: HeaderMap,
+    ) -> Result<Session, Error> {
+        extra_headers.remove("apikey");
+        extra_headers.remove(CONTENT_TYPE);
+
+        let request = self
+            .json_request(Method::POST, "/token", &body)?
+            .query(&[("grant_type", grant_type)])
+            .headers(extra_headers);
+
+        let reply = self.send(request)?;
+
+        if let Ok(session) = from_str::<Session>(&reply.body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Exchange refresh token for a new session
+    pub fn exchange_token_for_session(&self, refresh_token: &str) -> Result<Session, Error> {
+        self.token_grant(
+            "refresh_token",
+            serde_json::to_value(RefreshSessionPayload { refresh_token })?,
+        )
+    }
+
+    /// Exchange refresh token for a new session
+    pub fn refresh_session(&self, refresh_token: &str) -> Result<Session, Error> {
+        self.exchange_token_for_session(refresh_token)
+    }
+
+    /// Refresh the stored session with its own refresh token, storing and returning the new
+    /// session. Concurrent calls share a single refresh.
+    pub fn refresh_current_session(&self) -> Result<Session, Error> {
+        let session = self.stored_session()?;
+
+        self.refresh_stored_session(&session)
+    }
+
+    /// Bootstrap an empty client from a persisted refresh token. A revoked, already used, or
+    /// expired refresh token returns [`Error::InvalidRefreshToken`].
+    pub fn restore_from_refresh_token(&self, refresh_token: &str) -> Result<Session, Error> {
+        match self.exchange_token_for_session(refresh_token) {
+            Err(Error::AuthError { code, message, .. })
+                if is_invalid_refresh_token(code.as_deref(), &message) =>
+            {
+                Err(Error::InvalidRefreshToken)
+            }
+            result => result,
+        }
+    }
+
+    /// Exchange code for a new session
+    pub fn exchange_code_for_session(
+        &self,
+        auth_code: &str,
+        code_verifier: &str,
+    ) -> Result<Session, Error> {
+        self.token_grant(
+            "pkce",
+            serde_json::to_value(ExchangeCodeForSessionPayload {
+                auth_code,
+                code_verifier,
+            })?,
+        )
+    }
+
+    /// Exchange the `code` of a PKCE callback URL for a session. Errors GoTrue put in the
+    /// callback URL are returned as [`Error::AuthError`].
+    pub fn exchange_code_from_url(
+        &self,
+        callback_url: &str,
+        code_verifier: &str,
+    ) -> Result<Session, Error> {
+        let code = code_from_callback_url(callback_url)?;
+
+        self.exchange_code_for_session(&code, code_verifier)
+    }
+
+    /// Send a password recovery email
+    ///
+    /// Returns [`Error::RateLimited`] when too many emails were sent.
+    pub fn reset_password_for_email(
+        &self,
+        email: &str,
+        options: Option<ResetPasswordOptions>,
+    ) -> Result<(), Error> {
+        let redirect_to = options
+            .as_ref()
+            .and_then(|o| o.email_redirect_to.as_deref().map(str::to_owned));
+
+        let payload = ResetPasswordForEmailPayload {
+            email: String::from(email),
+            options,
+        };
+
+        let request = self
+            .json_request(Method::POST, "/recover", &payload)?
+            .query(&[("redirect_to", redirect_to.as_deref())]);
+
+        let reply = self.send(request)?;
+
+        reply.check_rate_limit()?;
+
+        if reply.status.is_success() {
+            return Ok(());
+        }
+
+        Err(naming_redirect_to(
+            reply.into_error(Method::POST),
+            redirect_to.as_deref(),
+        ))
+    }
+
+    /// Send the signed in user a nonce to confirm a password change with, by email or SMS
+    pub fn reauthenticate(&self, bearer_token: &str) -> Result<(), Error> {
+        let request = with_bearer(self.request(Method::GET, "/reauthenticate")?, bearer_token)?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        reply.check_rate_limit()?;
+
+        if reply.status.is_success() {
+            return Ok(());
+        }
+
+        Err(reply.into_error(Method::GET))
+    }
+
+    /// Resends emails for existing signup confirmation, email change, SMS OTP, or phone change OTP.
+    pub fn resend(&self, credentials: ResendParams) -> Result<(), Error> {
+        let reply = self.send(self.json_request(Method::POST, "/resend", &credentials)?)?;
+
+        if reply.status.is_success() {
+            return Ok(());
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Resends the signup confirmation email
+    pub fn resend_signup_confirmation(&self, email: &str) -> Result<(), Error> {
+        self.resend(ResendParams {
+            otp_type: OtpType::Signup,
+            email: email.to_owned(),
+            options: None,
+        })
+    }
+
+    /// Logs out a user with a given scope, [`LogoutScope::Global`] by default. The stored
+    /// session is cleared unless the scope is `Others`.
+    pub fn logout(&self, scope: Option<LogoutScope>, bearer_token: &str) -> Result<(), Error> {
+        let scope = scope.unwrap_or_default();
+
+        let request = with_bearer(
+            self.request(Method::POST, "/logout")?
+                .query(&[("scope", &scope)]),
+            bearer_token,
+        )?;
+
+        let reply = self.send_with_bearer(request, bearer_token)?;
+
+        if reply.status.is_success() {
+            if scope != LogoutScope::Others {
+                self.clear_session();
+            }
+            return Ok(());
+        }
+
+        Err(reply.into_error(Method::POST))
+    }
+
+    /// Sign out of all devices and clear the stored session
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn revoke_all_sessions(&self) -> Result<(), Error> {
+        let session = self.stored_session()?;
+
+        self.logout(Some(LogoutScope::Global), &session.access_token)
+    }
+
+    /// Initiates an SSO Login Flow, returning the URL where the user must authenticate with the
+    /// SSO Provider
+    ///
+    /// WARNING: Requires an SSO Provider and Supabase Pro plan
+    pub fn sso(&self, params: LoginWithSSO) -> Result<Url, Error> {
+        let payload = LoginWithSSOPayload::from(&params);
+
+        let reply = self.send(self.json_request(Method::POST, "/sso", &payload)?)?;
+
+        if reply.status.is_server_error() || reply.status.is_client_error() {
+            return Err(reply.into_error(Method::POST));
+        }
+
+        Ok(reply.url)
+    }
+
+    /// Get the session stored by the last successful login, sign up, or refresh
+    pub fn session(&self) -> Option<Session> {
+        self.session.read().ok().and_then(|session| session.clone())
+    }
+
+    /// Seed the client with tokens obtained elsewhere, e.g. restored from a cookie. The session
+    /// is stored and returned.
+    pub fn set_session(&self, access_token: &str, refresh_token: &str) -> Result<Session, Error> {
+        let claims = decode_jwt_unverified(access_token)?;
+
+        let now = self.unix_now();
+
+        if claims.exp <= now {
+            return self.refresh_session(refresh_token);
+        }
+
+        let user = self.get_user(access_token)?;
+
+        let session = Session {
+            access_token: access_token.to_string(),
+            token_type: "bearer".to_string(),
+            expires_in: (claims.exp - now) as i64,
+            expires_at: claims.exp,
+            refresh_token: refresh_token.to_string(),
+            user,
+            ..Default::default()
+        };
+
+        self.store_session(&session);
+
+        Ok(session)
+    }
+
+    /// Store a session you already have, without contacting the server. Does nothing if the
+    /// client was built with [`persist_session(false)`](AuthClientBuilder::persist_session).
+    pub fn set_session_from_struct(&self, session: Session) {
+        self.store_session(&session);
+    }
+
+    /// Snapshot the stored session, e.g. to persist it across restarts
+    pub fn export_session(&self) -> Option<Session> {
+        self.session()
+    }
+
+    /// Restore a session exported with [`export_session`](AuthClient::export_session)
+    pub fn import_session(&self, session: Session) {
+        self.store_session(&session);
+    }
+
+    /// Returns true if the client holds a session
+    pub fn is_authenticated(&self) -> bool {
+        self.session().is_some()
+    }
+
+    /// Returns true if the stored session's access token has expired, by the client's
+    /// [`Clock`].
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn session_expired(&self) -> Result<bool, Error> {
+        self.session_expires_within(Duration::ZERO)
+    }
+
+    /// Returns true if the stored session's access token expires within `margin`, by the
+    /// client's [`Clock`].
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn session_expires_within(&self, margin: Duration) -> Result<bool, Error> {
+        let session = self.stored_session()?;
+
+        Ok(session.expires_within_at(margin, self.clock.now()))
+    }
+
+    /// Headers authenticating requests to other Supabase APIs as the signed in user
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn auth_headers(&self) -> Result<HeaderMap, Error> {
+        let session = self.stored_session()?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", session.access_token))?,
+        );
+
+        Ok(headers)
+    }
+
+    /// Read the session from the `sb-access-token` and `sb-refresh-token` cookies of a request.
+    /// Returns `None` if either cookie is missing. The session isn't stored.
+    pub fn session_from_cookies(&self, cookies: &HeaderMap) -> Result<Option<Session>, Error> {
+        session_from_cookies(cookies, &self.jwt_secret, self.unix_now())
+    }
+
+    /// `Set-Cookie` headers storing `session` in the `sb-access-token` and `sb-refresh-token`
+    /// cookies
+    pub fn session_to_set_cookie_headers(
+        &self,
+        session: &Session,
+    ) -> Vec<(HeaderName, HeaderValue)> {
+        session_to_set_cookie_headers(session, self.unix_now())
+    }
+
+    /// Returns true if the stored session has to be stepped up to `aal2`, because the user has
+    /// a verified MFA factor but the session was only authenticated with one factor.
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn needs_mfa(&self) -> Result<bool, Error> {
+        let session = self.get_session()?;
+        let user = self.get_user(&session.access_token)?;
+
+        // The request may have refreshed the session, e.g. with `retry_on_401`
+        let session = self.session().unwrap_or(session);
+
+        let current_level = session
+            .authenticator_assurance_level(&DecodingKey::from_secret(self.jwt_secret.as_bytes()))?
+            .unwrap_or(AuthenticatorAssuranceLevel::Aal1);
+
+        let next_level = if user.factors.iter().any(Factor::is_verified) {
+            AuthenticatorAssuranceLevel::Aal2
+        } else {
+            current_level
+        };
+
+        Ok(current_level < next_level)
+    }
+
+    /// Get the stored session, refreshing it first if it expires within the refresh margin.
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn get_session(&self) -> Result<Session, Error> {
+        let session = self.stored_session()?;
+
+        let margin = effective_refresh_margin(self.refresh_margin, &session);
+
+        if !session.expires_within_at(margin, self.clock.now()) {
+            return Ok(session);
+        }
+
+        self.refresh_stored_session(&session)
+    }
+
+    /// Wait until the user of the stored session has confirmed their email, polling every
+    /// `poll_interval`, and return the confirmed session. Returns [`Error::NotAuthenticated`]
+    /// right away if there is no stored session, and [`Error::ConfirmationTimeout`] if the
+    /// email isn't confirmed within `timeout`.
+    ///
+    /// Requests are cut short at the timeout. Failures to reach the server are retried on the
+    /// next poll, other errors are returned.
+    pub fn wait_for_confirmation(
+        &self,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<Session, Error> {
+        // Nothing to poll without a session
+        self.stored_session()?;
+
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                return Err(Error::ConfirmationTimeout);
+            }
+
+            let client = self.with_timeout(
+                self.request_timeout
+                    .map_or(remaining, |timeout| timeout.min(remaining)),
+            );
+
+            match client.get_session() {
+                Ok(session) if session.user.has_confirmed_email() => return Ok(session),
+                Ok(session) => match client.get_user(&session.access_token) {
+                    Ok(user) if user.has_confirmed_email() => {
+                        return match client.refresh_stored_session(&session) {
+                            Err(Error::Transport { .. }) if Instant::now() >= deadline => {
+                                Err(Error::ConfirmationTimeout)
+                            }
+                            result => result,
+                        };
+                    }
+                    Ok(_) | Err(Error::Transport { .. }) => {}
+                    Err(error) => return Err(error),
+                },
+                Err(Error::Transport { .. }) => {}
+                Err(error) => return Err(error),
+            }
+
+            thread::sleep(poll_interval.min(deadline.saturating_duration_since(Instant::now())));
+        }
+    }
+
+    /// Decode and verify the stored session's access token locally using the JWT secret
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn current_claims(&self) -> Result<Claims, Error> {
+        let session = self.stored_session()?;
+
+        self.decode_jwt(&session.access_token)
+    }
+
+    /// Decode and verify a JWT signed with the client's JWT secret, returning its claims
+    pub fn decode_jwt(&self, token: &str) -> Result<Claims, Error> {
+        self.decode_jwt_with_validation(token, JwtValidation::default())
+    }
+
+    /// Decode and verify a JWT signed with the client's JWT secret, enforcing the audience and
+    /// issuer in `validation`
+    pub fn decode_jwt_with_validation(
+        &self,
+        token: &str,
+        validation: JwtValidation,
+    ) -> Result<Claims, Error> {
+        decode_verified(&self.jwt_secret, token, &validation)
+    }
+
+    /// Verify a JWT signed with the client's JWT secret and return its `sub` claim, the user id
+    pub fn user_id_from_token(&self, token: &str) -> Result<Uuid, Error> {
+        let claims =
+            decode_verified::<SubjectClaims>(&self.jwt_secret, token, &JwtValidation::default())?;
+
+        Uuid::parse_str(&claims.sub).map_err(|_| Error::WrongToken)
+    }
+
+    /// Verify a JWT signed with the client's JWT secret and build the user from its claims,
+    /// without asking the server
+    pub fn user_from_validated_token(&self, token: &str) -> Result<User, Error> {
+        user_from_claims(self.decode_jwt(token)?)
+    }
+
+    /// Returns a client that applies `timeout` to each of its requests. The returned client
+    /// shares the connection pool and stored session with the original.
+    pub fn with_timeout(&self, timeout: Duration) -> AuthClient {
+        AuthClient {
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a client that sends `api_key` as the `apikey` header instead of the stored key.
+    /// The returned client shares the connection pool and stored session with the original.
+    pub fn with_api_key(&self, api_key: &str) -> AuthClient {
+        AuthClient {
+            api_key: api_key.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Get the project URL from an AuthClient
+    pub fn project_url(&self) -> &str {
+        &self.project_url
+    }
+
+    /// Get the path GoTrue is served under, `/auth/v1` unless configured otherwise
+    pub fn auth_path(&self) -> &str {
+        &self.auth_path
+    }
+
+    /// Get the API Key from an AuthClient
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    /// Get the JWT Secret from an AuthClient
+    pub fn jwt_secret(&self) -> &str {
+        &self.jwt_secret
+    }
+
+    /// Refresh the stored session `stale`, one caller at a time.
+    ///
+    /// Callers that waited for another refresh get the session it stored instead of spending the
+    /// rotated refresh token again.
+    fn refresh_stored_session(&self, stale: &Session) -> Result<Session, Error> {
+        let _guard = self
+            .refresh_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match self.session() {
+            Some(current) if current.refresh_token != stale.refresh_token => Ok(current),
+            Some(current) => self.refresh_session(&current.refresh_token),
+            None => Err(Error::NotAuthenticated),
+        }
+    }
+
+    /// The stored session, or why there is none
+    fn stored_session(&self) -> Result<Session, Error> {
+        match self.session() {
+            Some(session) => Ok(session),
+            None if !self.persist_session => Err(Error::SessionNotPersisted),
+            None => Err(Error::NotAuthenticated),
+        }
+    }
+
+    fn store_session(&self, session: &Session) {
+        if !self.persist_session {
+            return;
+        }
+
+        warn_on_long_refresh_margin(self.refresh_margin, &self.margin_warned_for, session);
+
+        if let Ok(mut stored) = self.session.write() {
+            *stored = Some(session.clone());
+        }
+    }
+
+    fn clear_session(&self) {
+        if let Ok(mut stored) = self.session.write() {
+            *stored = None;
+        }
+    }
+
+    /// The client clock's current time as a unix timestamp
+    fn unix_now(&self) -> u64 {
+        unix_time(self.clock.now())
+    }
+
+    /// Start a request to `path` under the auth path, with the `apikey` header and the
+    /// per-request settings of this client applied
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, Error> {
+        let request = self
+            .client
+            .request(
+                method,
+                format!("{}{}{}", self.project_url, self.auth_path, path),
+            )
+            .header("apikey", HeaderValue::from_str(&self.api_key)?);
+
+        Ok(match self.request_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        })
+    }
+
+    /// [`request`](AuthClient::request) with `payload` as its JSON body
+    fn json_request<T: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        payload: &T,
+    ) -> Result<RequestBuilder, Error> {
+        Ok(self
+            .request(method, path)?
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(serde_json::to_string(payload)?))
+    }
+
+    /// Send `request` and read the response
+    fn send(&self, request: RequestBuilder) -> Result<Reply, Error> {
+        let response = request
+            .send()
+            .map_err(|error| self.transport_error(error))?;
+
+        self.read(response)
+    }
+
+    /// Send a request authenticated with `bearer_token`.
+    ///
+    /// With [`retry_on_401`](AuthClientBuilder::retry_on_401) set and `bearer_token` being the
+    /// stored session's access token, a 401 refreshes the session and replays the request once
+    /// with the new token. If the refresh fails the 401 response is returned as is.
+    fn send_with_bearer(
+        &self,
+        request: RequestBuilder,
+        bearer_token: &str,
+    ) -> Result<Reply, Error> {
+        let replay = if self.retry_on_401 {
+            request.try_clone()
+        } else {
+            None
+        };
+
+        let reply = self.send(request)?;
+
+        if reply.status != StatusCode::UNAUTHORIZED {
+            return Ok(reply);
+        }
+
+        let (Some(replay), Some(session)) = (replay, self.session()) else {
+            return Ok(reply);
+        };
+
+        if session.access_token != bearer_token {
+            return Ok(reply);
+        }
+
+        let Ok(refreshed) = self.refresh_stored_session(&session) else {
+            return Ok(reply);
+        };
+
+        let mut replay = replay.build()?;
+        replay.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", refreshed.access_token))?,
+        );
+
+        let response = self
+            .client
+            .execute(replay)
+            .map_err(|error| self.transport_error(error))?;
+
+        self.read(response)
+    }
+
+    /// Read the body of `response`, which the request timeout also applies to
+    fn read(&self, response: Response) -> Result<Reply, Error> {
+        let status = response.status();
+        let url = response.url().clone();
+        let headers = response.headers().clone();
+
+        let body = response
+            .text()
+            .map_err(|error| self.transport_error(error))?;
+
+        Ok(Reply {
+            status,
+            url,
+            headers,
+            body,
+        })
+    }
+
+    /// A transport error carrying this client's request timeout
+    fn transport_error(&self, source: reqwest::Error) -> Error {
+        Error::Transport {
+            source,
+            timeout: self.request_timeout,
+        }
+    }
+}
+
+impl AuthClientBuilder {
+    /// Use your own `reqwest::blocking::Client`, e.g. to configure proxies, timeouts, or custom
+    /// root certificates. The client is used as-is, regardless of the enabled TLS feature.
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// How long before its expiry the stored session is refreshed by
+    /// [`AuthClient::get_session`]. Defaults to 60 seconds, capped to half of the session's
+    /// lifetime.
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// How long [`AuthClient::get_settings`] reuses the last fetched settings. Defaults to
+    /// 5 minutes, use [`Duration::ZERO`] to disable the cache.
+    pub fn settings_ttl(mut self, ttl: Duration) -> Self {
+        self.settings_ttl = ttl;
+        self
+    }
+
+    /// The path GoTrue is served under, relative to the project URL. Defaults to `/auth/v1`.
+    pub fn auth_path(mut self, path: impl Into<String>) -> Self {
+        self.auth_path = path.into();
+        self
+    }
+
+    /// The clock used to decide when sessions expire, e.g. a [`MockClock`](crate::clock::MockClock)
+    /// in tests. Defaults to the system clock.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Whether the client stores the sessions returned by logins, sign ups, and refreshes.
+    /// Defaults to true.
+    pub fn persist_session(mut self, persist_session: bool) -> Self {
+        self.persist_session = persist_session;
+        self
+    }
+
+    /// Whether a 401 to a request made with the stored session's access token refreshes the
+    /// session and replays the request with the new token. Defaults to false.
+    pub fn retry_on_401(mut self, retry_on_401: bool) -> Self {
+        self.retry_on_401 = retry_on_401;
+        self
+    }
+
+    /// Build the blocking [`AuthClient`]
+    ///
+    /// Trailing slashes are trimmed from the project URL. Returns [`Error::ParseUrlError`] if it
+    /// isn't a valid URL.
+    ///
+    /// # Panics
+    /// Without an [`http_client`](AuthClientBuilder::http_client), panics if called from within
+    /// an async runtime, like `reqwest::blocking::Client::new`.
+    pub fn build(self) -> Result<AuthClient, Error> {
+        let project_url = self.project_url.trim_end_matches('/').to_owned();
+        Url::parse(&project_url).map_err(|_| Error::ParseUrlError)?;
+
+        Ok(AuthClient {
+            client: self.http_client.unwrap_or_default(),
+            project_url,
+            api_key: self.api_key,
+            jwt_secret: self.jwt_secret,
+            session: Arc::default(),
+            request_timeout: None,
+            refresh_margin: self.refresh_margin,
+            settings_cache: Arc::default(),
+            settings_ttl: self.settings_ttl,
+            auth_path: self.auth_path.trim_end_matches('/').to_owned(),
+            clock: self.clock,
+            refresh_lock: Arc::default(),
+            persist_session: self.persist_session,
+            margin_warned_for: Arc::default(),
+            retry_on_401: self.retry_on_401,
+        })
+    }
+}
+
+impl Reply {
+    /// [`Error::RateLimited`] if the server rate limited the request
+    fn check_rate_limit(&self) -> Result<(), Error> {
+        if self.status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(&self.headers),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The error GoTrue responded with, or the raw response if the body isn't one
+    fn into_error(self, method: Method) -> Error {
+        match from_str::<SupabaseHTTPError>(&self.body) {
+            Ok(error) => Error::AuthError {
+                status: self.status,
+                code: error.error_code,
+                message: error.message,
+            },
+            Err(_) => self.into_unexpected(method),
+        }
+    }
+
+    /// The raw response as an error
+    fn into_unexpected(self, method: Method) -> Error {
+        Error::UnexpectedResponse {
+            status: self.status,
+            method,
+            path: self.url.path().to_owned(),
+            body: self.body,
+        }
+    }
+}
+
+/// Authenticate `request` with `bearer_token`
+fn with_bearer(request: RequestBuilder, bearer_token: &str) -> Result<RequestBuilder, Error> {
+    Ok(request.header(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+    ))
+}
+
+/// Name the `redirect_to` in a redirect related [`Error::AuthError`]
+fn naming_redirect_to(error: Error, redirect_to: Option<&str>) -> Error {
+    match error {
+        Error::AuthError {
+            status,
+            code,
+            message,
+        } => Error::AuthError {
+            message: with_redirect_to(message, code.as_deref(), redirect_to),
+            status,
+            code,
+        },
+        error => error,
+    }
+}
+
+// Implement custom Debug to avoid exposing sensitive information
+impl fmt::Debug for AuthClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only report whether a session exists, its tokens are as sensitive as the keys
+        f.debug_struct("AuthClient")
+            .field("project_url", &self.project_url)
+            .field("auth_path", &self.auth_path)
+            .field("api_key", &"***")
+            .field("jwt_secret", &"***")
+            .field("has_session", &self.is_authenticated())
+            .field("persist_session", &self.persist_session)
+            .field("request_timeout", &self.request_timeout)
+            .field("refresh_margin", &self.refresh_margin)
+            .field("retry_on_401", &self.retry_on_401)
+            .finish()
+    }
+}
+
+// Implement custom Debug to avoid exposing sensitive information
+impl fmt::Debug for AuthClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthClientBuilder")
+            .field("project_url", &self.project_url)
+            .field("api_key", &"***")
+            .field("jwt_secret", &"***")
+            .field("http_client", &self.http_client)
+            .field("refresh_margin", &self.refresh_margin)
+            .field("settings_ttl", &self.settings_ttl)
+            .field("auth_path", &self.auth_path)
+            .field("clock", &self.clock)
+            .field("persist_session", &self.persist_session)
+            .field("retry_on_401", &self.retry_on_401)
+            .finish()
+    }
+}
@@ -13,29 +13,52 @@ through the [`AuthClient`] struct.
 - Properly handle token expiration and refresh cycles
 */
 
-use std::env;
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use jsonwebtoken::DecodingKey;
 
 use reqwest::{
-    header::{self, HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
-    Client, Url,
+    header::{self, HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Client, Method, RequestBuilder, Response, StatusCode, Url,
 };
-use serde_json::{from_str, Value};
+use serde_json::{from_str, Map, Value};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
 
 use crate::{
+    clock::{Clock, SystemClock},
     error::{
         Error::{self, AuthError},
         SupabaseHTTPError,
     },
     models::{
-        AuthClient, AuthServerHealth, AuthServerSettings, EmailSignUpConfirmation,
-        EmailSignUpResult, ExchangeCodeForSessionPayload, IdTokenCredentials, InviteParams,
+        AdminUserAttributes, AuthClient, AuthClientBuilder, AuthServerHealth, AuthServerSettings,
+        AuthenticatorAssuranceLevel, Channel, Claims, CreateSsoProviderParams,
+        CreateSsoProviderPayload, EmailChangeResponse, EmailOtpType, EmailSignUpConfirmation,
+        EmailSignUpResult, ExchangeCodeForSessionPayload, Factor, GotrueMetaSecurity,
+        IdTokenCredentials, InviteParams, JwtValidation, ListUsersParams, ListUsersResponse,
         LoginAnonymouslyOptions, LoginAnonymouslyPayload, LoginEmailOtpParams,
-        LoginWithEmailAndPasswordPayload, LoginWithEmailOtpPayload, LoginWithOAuthOptions,
-        LoginWithPhoneAndPasswordPayload, LoginWithSSO, LogoutScope, OAuthResponse, OTPResponse,
-        Provider, RefreshSessionPayload, RequestMagicLinkPayload, ResendParams,
-        ResetPasswordForEmailPayload, ResetPasswordOptions, SendSMSOtpPayload, Session,
-        SignUpWithEmailAndPasswordPayload, SignUpWithPasswordOptions,
-        SignUpWithPhoneAndPasswordPayload, UpdatedUser, User, VerifyOtpParams, AUTH_V1,
+        LoginMobileOtpParams, LoginWithEmailAndPasswordPayload, LoginWithEmailOtpPayload,
+        LoginWithOAuthOptions, LoginWithPasswordOptions, LoginWithPhoneAndPasswordPayload,
+        LoginWithSSO, LoginWithSSOPayload, LogoutScope, OAuthResponse, OTPResponse, OtpTarget,
+        OtpType, PasswordPolicy, Provider, RefreshSessionPayload, RequestMagicLinkPayload,
+        ResendParams, ResetPasswordForEmailPayload, ResetPasswordOptions, SendSMSOtpPayload,
+        Session, SignUpWithEmailAndPasswordPayload, SignUpWithPasswordOptions,
+        SignUpWithPhoneAndPasswordPayload, SsoProvider, SsoProvidersResponse, SubjectClaims,
+        UpdateUserResult, UpdatedUser, User, VerifyOtpParams, AUTH_V1, DEFAULT_MAX_REFRESH_BACKOFF,
+        DEFAULT_REFRESH_MARGIN, DEFAULT_REFRESH_RETRY_DELAY, DEFAULT_SETTINGS_TTL,
+    },
+    shared::{
+        code_from_callback_url, decode_jwt_unverified, decode_verified, effective_refresh_margin,
+        format_ban_duration, is_invalid_refresh_token, oauth_url, parse_success_body,
+        password_policy, retry_after, session_from_cookies, session_to_set_cookie_headers,
+        unix_time, user_from_claims, verify_oauth_state, warn_on_long_refresh_margin,
+        with_redirect_to,
     },
 };
 
@@ -50,12 +73,36 @@ impl AuthClient {
         project_url: impl Into<String>,
         api_key: impl Into<String>,
         jwt_secret: impl Into<String>,
-    ) -> Self {
-        AuthClient {
-            client: Client::new(),
+    ) -> Result<Self, Error> {
+        AuthClient::builder(project_url, api_key, jwt_secret).build()
+    }
+
+    /// Create a builder to configure a new Auth Client
+    /// # Example
+    /// ```
+    /// let auth_client = AuthClient::builder(project_url, api_key, jwt_secret)
+    ///     .http_client(reqwest::Client::new())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(
+        project_url: impl Into<String>,
+        api_key: impl Into<String>,
+        jwt_secret: impl Into<String>,
+    ) -> AuthClientBuilder {
+        AuthClientBuilder {
             project_url: project_url.into(),
             api_key: api_key.into(),
             jwt_secret: jwt_secret.into(),
+            http_client: None,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            settings_ttl: DEFAULT_SETTINGS_TTL,
+            auth_path: AUTH_V1.to_string(),
+            clock: Arc::new(SystemClock),
+            persist_session: true,
+            refresh_backoff: (DEFAULT_REFRESH_RETRY_DELAY, DEFAULT_MAX_REFRESH_BACKOFF),
+            jitter_seed: None,
+            retry_on_401: false,
         }
     }
 
@@ -72,15 +119,12 @@ impl AuthClient {
         let api_key = env::var("SUPABASE_API_KEY")?;
         let jwt_secret = env::var("SUPABASE_JWT_SECRET")?;
 
-        Ok(AuthClient {
-            client: Client::new(),
-            project_url,
-            api_key,
-            jwt_secret,
-        })
+        AuthClient::builder(project_url, api_key, jwt_secret).build()
     }
 
     /// Sign in a user with an email and password
+    ///
+    /// Returns [`Error::EmailNotConfirmed`] if the user hasn't confirmed their email yet.
     /// # Example
     /// ```
     /// let session = auth_client
@@ -91,43 +135,68 @@ impl AuthClient {
     /// assert!(session.user.email == demo_email)
     /// ```
     pub async fn login_with_email(&self, email: &str, password: &str) -> Result<Session, Error> {
-        let payload = LoginWithEmailAndPasswordPayload { email, password };
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        let body = serde_json::to_string(&payload)?;
-
-        let response = self
-            .client
-            .post(format!(
-                "{}{}/token?grant_type=password",
-                self.project_url, AUTH_V1
-            ))
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?;
+        self.login_with_email_with_options(email, password, None)
+            .await
+    }
 
-        let res_status = response.status();
-        let res_body = response.text().await?;
+    /// Sign in a user with an email and password, passing a captcha token
+    /// # Example
+    /// ```
+    /// let options = LoginWithPasswordOptions {
+    ///     captcha_token: Some(captcha_token),
+    /// };
+    ///
+    /// let session = auth_client
+    ///     .login_with_email_with_options(demo_email, demo_password, Some(options))
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn login_with_email_with_options(
+        &self,
+        email: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+    ) -> Result<Session, Error> {
+        self.login_with_email_with_headers(email, password, options, HeaderMap::new())
+            .await
+    }
 
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
-        }
+    /// Sign in a user with an email and password, sending `extra_headers` with this request
+    /// only, e.g. a device fingerprint a WAF in front of GoTrue requires.
+    ///
+    /// The extra headers can't replace the `apikey` or `Content-Type` headers.
+    /// # Example
+    /// ```
+    /// let mut extra_headers = HeaderMap::new();
+    /// extra_headers.insert("x-device-fingerprint", HeaderValue::from_str(&fingerprint)?);
+    ///
+    /// let session = auth_client
+    ///     .login_with_email_with_headers(demo_email, demo_password, None, extra_headers)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn login_with_email_with_headers(
+        &self,
+        email: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+        extra_headers: HeaderMap,
+    ) -> Result<Session, Error> {
+        let payload = LoginWithEmailAndPasswordPayload {
+            email,
+            password,
+            gotrue_meta_security: options.map(GotrueMetaSecurity::from),
+        };
 
-        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
-            return Err(Error::AuthError {
-                status: res_status,
-                message: error.message,
-            });
+        match self
+            .token_grant_with_headers("password", serde_json::to_value(&payload)?, extra_headers)
+            .await
+        {
+            Err(Error::AuthError {
+                code: Some(code), ..
+            }) if code == "email_not_confirmed" => Err(Error::EmailNotConfirmed),
+            result => result,
         }
-
-        // Fallback: return raw error
-        Err(Error::AuthError {
-            status: res_status,
-            message: res_body,
-        })
     }
 
     /// Sign in a user with phone number and password
@@ -141,55 +210,50 @@ impl AuthClient {
     /// assert!(session.user.phone == demo_phone)
     /// ```
     pub async fn login_with_phone(&self, phone: &str, password: &str) -> Result<Session, Error> {
-        let payload = LoginWithPhoneAndPasswordPayload { phone, password };
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
-        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-
-        let body = serde_json::to_string(&payload)?;
-
-        let response = self
-            .client
-            .post(format!(
-                "{}{}/token?grant_type=password",
-                self.project_url, AUTH_V1
-            ))
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?;
-
-        let res_status = response.status();
-        let res_body = response.text().await?;
-
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
-        }
+        self.login_with_phone_with_options(phone, password, None)
+            .await
+    }
 
-        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
-            return Err(Error::AuthError {
-                status: res_status,
-                message: error.message,
-            });
-        }
+    /// Sign in a user with phone number and password, passing a captcha token
+    /// # Example
+    /// ```
+    /// let options = LoginWithPasswordOptions {
+    ///     captcha_token: Some(captcha_token),
+    /// };
+    ///
+    /// let session = auth_client
+    ///     .login_with_phone_with_options(demo_phone, demo_password, Some(options))
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn login_with_phone_with_options(
+        &self,
+        phone: &str,
+        password: &str,
+        options: Option<LoginWithPasswordOptions>,
+    ) -> Result<Session, Error> {
+        let payload = LoginWithPhoneAndPasswordPayload {
+            phone,
+            password,
+            gotrue_meta_security: options.map(GotrueMetaSecurity::from),
+        };
 
-        // Fallback: return raw error
-        Err(Error::AuthError {
-            status: res_status,
-            message: res_body,
-        })
+        self.token_grant("password", serde_json::to_value(&payload)?)
+            .await
     }
 
     /// Sign up a new user with an email and password
     /// # Example
     /// ```
+    /// let options = SignUpWithPasswordOptions {
+    ///     data: Some(json!({ "display_name": "Demo" })),
+    ///     ..Default::default()
+    /// };
+    ///
     /// let result = auth_client
-    ///     .sign_up_with_email_and_password(demo_email, demo_password)
+    ///     .sign_up_with_email_and_password(demo_email, demo_password, Some(options))
     ///     .await
     ///     .unwrap();
-    ///
-    /// assert!(result.session.user.email == demo_email)
     ///```
     pub async fn sign_up_with_email_and_password(
         &self,
@@ -214,18 +278,23 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/signup", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/signup", self.project_url, self.auth_path),
+            )
             .query(&[("redirect_to", redirect_to.as_deref())])
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if let Ok(session) = from_str::<Session>(&res_body) {
+            self.store_session(&session);
             return Ok(EmailSignUpResult::SessionResult(session));
         }
 
@@ -234,24 +303,33 @@ impl AuthClient {
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            let message = with_redirect_to(
+                error.message,
+                error.error_code.as_deref(),
+                redirect_to.as_deref(),
+            );
+
             return Err(Error::AuthError {
                 status: res_status,
-                message: error.message,
+                code: error.error_code,
+                message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Sign up a new user with an email and password
+    /// Sign up a new user with a phone number and password
     /// # Example
     /// ```
     /// let session = auth_client
-    ///     .sign_up_with_phone_and_password(demo_phone, demo_password)
+    ///     .sign_up_with_phone_and_password(demo_phone, demo_password, None)
     ///     .await
     ///     .unwrap();
     ///
@@ -280,32 +358,46 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/signup", self.project_url, AUTH_V1))
-            .query(&[("email_redirect_to", redirect_to.as_deref())])
+            .request(
+                Method::POST,
+                format!("{}{}/signup", self.project_url, self.auth_path),
+            )
+            .query(&[("redirect_to", redirect_to.as_deref())])
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
+        if let Ok(session) = from_str::<Session>(&res_body) {
+            self.store_session(&session);
             return Ok(session);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            let message = with_redirect_to(
+                error.message,
+                error.error_code.as_deref(),
+                redirect_to.as_deref(),
+            );
+
             return Err(Error::AuthError {
                 status: res_status,
-                message: error.message,
+                code: error.error_code,
+                message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
@@ -316,14 +408,22 @@ impl AuthClient {
     ///
     /// This method requires anonymous sign in to be enabled in your dashboard.
     ///
+    /// Every call creates a new anonymous user. To resume an anonymous user instead, check
+    /// [`session`](AuthClient::session) for a stored session of an anonymous user first, and
+    /// show onboarding only when you had to create one.
+    ///
     /// # Example
     /// ```
-    /// let session = auth_client
-    ///     .login_anonymously(demo_options)
-    ///     .await
-    ///     .unwrap();
+    /// let session = match auth_client.session() {
+    ///     Some(session) if session.user.is_anonymous() => session,
+    ///     _ => {
+    ///         let session = auth_client.login_anonymously(None).await.unwrap();
+    ///         show_onboarding();
+    ///         session
+    ///     }
+    /// };
     ///
-    /// assert!(session.user.user_metadata.display_name == demo_options.data.display_name)
+    /// assert!(session.user.is_anonymous());
     /// ```
     pub async fn login_anonymously(
         &self,
@@ -338,31 +438,39 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/signup", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/signup", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
+        if let Ok(session) = from_str::<Session>(&res_body) {
+            self.store_session(&session);
             return Ok(session);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
@@ -384,15 +492,26 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/magiclink", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/magiclink", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+
+        if res_status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if res_status.is_success() {
             Ok(())
@@ -400,15 +519,18 @@ impl AuthClient {
             if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
                 return Err(AuthError {
                     status: res_status,
+                    code: error.error_code,
                     message: error.message,
                 });
             }
 
             // Fallback: return raw error
-            return Err(AuthError {
+            Err(Error::UnexpectedResponse {
                 status: res_status,
-                message: res_body,
-            });
+                method: Method::POST,
+                path: res_path,
+                body: res_body,
+            })
         }
     }
 
@@ -419,7 +541,63 @@ impl AuthClient {
     /// let response = auth_client.send_sms_with_otp(demo_phone).await;
     /// ```
     pub async fn send_sms_with_otp(&self, phone: &str) -> Result<OTPResponse, Error> {
-        let payload = SendSMSOtpPayload { phone };
+        self.send_phone_otp(phone, Channel::Sms).await
+    }
+
+    /// Send a Login OTP to a phone number over `channel`, SMS or WhatsApp
+    ///
+    /// WhatsApp requires a provider that supports it, like Twilio, to be configured for the project.
+    /// # Example
+    /// ```
+    /// let response = auth_client
+    ///     .send_phone_otp(demo_phone, Channel::Whatsapp)
+    ///     .await;
+    /// ```
+    pub async fn send_phone_otp(
+        &self,
+        phone: &str,
+        channel: Channel,
+    ) -> Result<OTPResponse, Error> {
+        let options = LoginMobileOtpParams {
+            channel: Some(channel),
+            ..Default::default()
+        };
+
+        self.send_sms_with_otp_with_options(phone, Some(options))
+            .await
+    }
+
+    /// Send a Login OTP to a phone number with options, the phone counterpart of
+    /// [`send_email_with_otp`](AuthClient::send_email_with_otp)
+    /// # Example
+    /// ```
+    /// let options = LoginMobileOtpParams {
+    ///     channel: Some(Channel::Whatsapp),
+    ///     should_create_user: Some(false),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let response = auth_client
+    ///     .send_sms_with_otp_with_options(demo_phone, Some(options))
+    ///     .await;
+    /// ```
+    pub async fn send_sms_with_otp_with_options(
+        &self,
+        phone: &str,
+        options: Option<LoginMobileOtpParams>,
+    ) -> Result<OTPResponse, Error> {
+        let gotrue_meta_security = options
+            .as_ref()
+            .and_then(|options| options.captcha_token.clone())
+            .map(|captcha_token| GotrueMetaSecurity {
+                captcha_token: Some(captcha_token),
+            });
+
+        let payload = SendSMSOtpPayload {
+            phone,
+            options,
+            gotrue_meta_security,
+        };
 
         let mut headers = header::HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
@@ -428,32 +606,45 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/otp", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/otp", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+
+        if res_status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if res_status.is_success() {
-            let message = serde_json::from_str(&res_body)?;
-            Ok(message)
+            parse_success_body(&res_body)
         } else {
             if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
                 return Err(AuthError {
                     status: res_status,
+                    code: error.error_code,
                     message: error.message,
                 });
             }
 
             // Fallback: return raw error
-            return Err(AuthError {
+            Err(Error::UnexpectedResponse {
                 status: res_status,
-                message: res_body,
-            });
+                method: Method::POST,
+                path: res_path,
+                body: res_body,
+            })
         }
     }
 
@@ -469,7 +660,18 @@ impl AuthClient {
         email: &str,
         options: Option<LoginEmailOtpParams>,
     ) -> Result<OTPResponse, Error> {
-        let payload = LoginWithEmailOtpPayload { email, options };
+        let gotrue_meta_security = options
+            .as_ref()
+            .and_then(|options| options.captcha_token.clone())
+            .map(|captcha_token| GotrueMetaSecurity {
+                captcha_token: Some(captcha_token),
+            });
+
+        let payload = LoginWithEmailOtpPayload {
+            email,
+            options,
+            gotrue_meta_security,
+        };
 
         let mut headers = header::HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
@@ -478,32 +680,64 @@ impl AuthClient {
         let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/otp", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/otp", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+
+        if res_status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if res_status.is_success() {
-            let message = serde_json::from_str(&res_body)?;
-            Ok(message)
+            parse_success_body(&res_body)
         } else {
             if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
                 return Err(AuthError {
                     status: res_status,
+                    code: error.error_code,
                     message: error.message,
                 });
             }
 
             // Fallback: return raw error
-            return Err(AuthError {
+            Err(Error::UnexpectedResponse {
                 status: res_status,
-                message: res_body,
-            });
+                method: Method::POST,
+                path: res_path,
+                body: res_body,
+            })
+        }
+    }
+
+    /// Send a Login OTP to an email address or phone number
+    /// # Example
+    /// ```
+    /// let target = OtpTarget::Phone {
+    ///     phone: demo_phone.to_string(),
+    ///     options: None,
+    /// };
+    ///
+    /// let response = auth_client.send_otp(target).await.unwrap();
+    /// ```
+    pub async fn send_otp(&self, target: OtpTarget) -> Result<OTPResponse, Error> {
+        match target {
+            OtpTarget::Email { email, options } => self.send_email_with_otp(&email, options).await,
+            OtpTarget::Phone { phone, options } => {
+                self.send_sms_with_otp_with_options(&phone, options).await
+            }
         }
     }
 
@@ -517,8 +751,9 @@ impl AuthClient {
     /// let options = LoginWithOAuthOptions {
     ///     query_params: Some(params),
     ///     redirect_to: Some("localhost".to_string()),
-    ///     scopes: Some("repo gist notifications".to_string()),
+    ///     scopes: Some(vec!["repo".to_string(), "gist".to_string()]),
     ///     skip_browser_redirect: Some(true),
+    ///     ..Default::default()
     /// };
     ///
     /// let response = auth_client
@@ -530,30 +765,7 @@ impl AuthClient {
         provider: Provider,
         options: Option<LoginWithOAuthOptions>,
     ) -> Result<OAuthResponse, Error> {
-        let query_params = options.as_ref().map_or_else(
-            || vec![("provider", provider.to_string())],
-            |o| {
-                let mut params = vec![("provider", provider.to_string())];
-
-                if let Some(ref redirect) = o.redirect_to {
-                    params.push(("redirect_to", redirect.to_string()));
-                }
-
-                if let Some(ref extra) = o.query_params {
-                    params.extend(extra.iter().map(|(k, v)| (k.as_str(), v.to_string())));
-                }
-
-                params
-            },
-        );
-
-        let url = Url::parse_with_params(
-            format!("{}{}/authorize", self.project_url, AUTH_V1).as_str(),
-            query_params,
-        )
-        .map_err(|_| Error::ParseUrlError)?;
-
-        Ok(OAuthResponse { url, provider })
+        oauth_url(&self.project_url, &self.auth_path, provider, options)
     }
 
     /// Sign up a user using an OAuth provider.
@@ -566,8 +778,9 @@ impl AuthClient {
     /// let options = LoginWithOAuthOptions {
     ///     query_params: Some(params),
     ///     redirect_to: Some("localhost".to_string()),
-    ///     scopes: Some("repo gist notifications".to_string()),
+    ///     scopes: Some(vec!["repo".to_string(), "gist".to_string()]),
     ///     skip_browser_redirect: Some(true),
+    ///     ..Default::default()
     /// };
     ///
     /// let response = auth_client
@@ -582,6 +795,26 @@ impl AuthClient {
         self.login_with_oauth(provider, options)
     }
 
+    /// Verify that the `state` returned to your OAuth callback matches the one generated by
+    /// [`AuthClient::login_with_oauth`]. The state is read from the query string, or from the
+    /// fragment if the query doesn't contain one.
+    /// # Example
+    /// ```
+    /// let oauth_response = auth_client.login_with_oauth(Provider::Github, None).unwrap();
+    ///
+    /// // Later, in your callback handler
+    /// auth_client
+    ///     .verify_oauth_state(callback_url, &oauth_response.state)
+    ///     .unwrap();
+    /// ```
+    pub fn verify_oauth_state(
+        &self,
+        callback_url: &str,
+        expected_state: &str,
+    ) -> Result<(), Error> {
+        verify_oauth_state(callback_url, expected_state)
+    }
+
     /// Return the signed in User
     /// # Example
     /// ```
@@ -600,15 +833,18 @@ impl AuthClient {
             HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
         );
 
-        let response = self
-            .client
-            .get(format!("{}{}/user", self.project_url, AUTH_V1))
-            .headers(headers)
-            .send()
-            .await?;
+        let request = self
+            .request(
+                Method::GET,
+                format!("{}{}/user", self.project_url, self.auth_path),
+            )
+            .headers(headers);
+
+        let response = self.send_with_bearer(request, bearer_token).await?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if let Ok(user) = from_str(&res_body) {
             return Ok(user);
@@ -617,140 +853,303 @@ impl AuthClient {
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Update the user, such as changing email or password. Each field (email, password, and data) is optional
+    /// Like [`get_user`](AuthClient::get_user), but returns the untyped JSON body, e.g. to read
+    /// fields [`User`] doesn't have yet
     /// # Example
     /// ```
-    /// let updated_user_data = UpdateUserPayload {
-    ///     email: Some("demo@demo.com".to_string()),
-    ///     password: Some("demo_password".to_string()),
-    ///     data: None, // This field can hold any valid JSON value
-    /// };
+    /// let user = auth_client.get_user_raw(&access_token).await.unwrap();
     ///
-    /// let user = auth_client
-    ///     .update_user(updated_user_data, access_token)
-    ///     .await
-    ///     .unwrap();
+    /// let new_field = &user["new_field"];
     /// ```
-    pub async fn update_user(
-        &self,
-        updated_user: UpdatedUser,
-        bearer_token: &str,
-    ) -> Result<User, Error> {
+    pub async fn get_user_raw(&self, bearer_token: &str) -> Result<Value, Error> {
         let mut headers = header::HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
         headers.insert(
             AUTHORIZATION,
             HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
         );
 
-        let body = serde_json::to_string::<UpdatedUser>(&updated_user)?;
+        let request = self
+            .request(
+                Method::GET,
+                format!("{}{}/user", self.project_url, self.auth_path),
+            )
+            .headers(headers);
 
-        let response = self
-            .client
-            .put(format!("{}{}/user", self.project_url, AUTH_V1))
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?;
+        let response = self.send_with_bearer(request, bearer_token).await?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(user) = from_str(&res_body) {
-            return Ok(user);
+        if res_status.is_success() {
+            return Ok(from_str(&res_body)?);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Allows signing in with an OIDC ID token. The authentication provider used should be enabled and configured.
+    /// Like [`get_user`](AuthClient::get_user), but returns `Ok(None)` when the server rejects
+    /// the token with 401 or 403, e.g. because it expired or the user was deleted
     /// # Example
     /// ```
-    /// let credentials = IdTokenCredentials {
-    ///     provider: Provider::Github,
-    ///     token: "<id-token-from-auth-provider>",
+    /// match auth_client.try_get_user(&access_token).await.unwrap() {
+    ///     Some(user) => println!("Signed in as {}", user.email),
+    ///     None => println!("Not signed in"),
+    /// }
+    /// ```
+    pub async fn try_get_user(&self, bearer_token: &str) -> Result<Option<User>, Error> {
+        match self.get_user(bearer_token).await {
+            Ok(user) => Ok(Some(user)),
+            Err(Error::AuthError { status, .. } | Error::UnexpectedResponse { status, .. })
+                if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Update the user, such as changing email or password. Each field (email, password, and data) is optional
+    ///
+    /// Only fields the user may change themselves can be sent here. Use
+    /// [`admin_update_user_by_id`](AuthClient::admin_update_user_by_id) for `app_metadata`,
+    /// `role`, or bans.
+    ///
+    /// A new email isn't applied right away, confirm it with
+    /// [`verify_email_change`](AuthClient::verify_email_change).
+    /// # Example
+    /// ```
+    /// let updated_user_data = UpdatedUser {
+    ///     email: Some("demo@demo.com".to_string()),
+    ///     password: Some("demo_password".to_string()),
+    ///     data: None, // This field can hold any valid JSON value
+    ///     ..Default::default()
     /// };
     ///
-    /// let session = auth_client
-    ///     .login_with_id_token(credentials)
+    /// let user = auth_client
+    ///     .update_user(updated_user_data, access_token)
     ///     .await
     ///     .unwrap();
     /// ```
-    pub async fn login_with_id_token(
+    pub async fn update_user(
         &self,
-        credentials: IdTokenCredentials,
-    ) -> Result<Session, Error> {
-        let mut headers = HeaderMap::new();
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<User, Error> {
+        self.update_user_with_session(updated_user, bearer_token)
+            .await
+            .map(|result| result.user)
+    }
+
+    /// Like [`update_user`](AuthClient::update_user), but also returns the new session if
+    /// GoTrue rotated the tokens, e.g. after a password change.
+    ///
+    /// Both methods store a returned session if `bearer_token` is the stored session's access
+    /// token, so the client doesn't keep using the old tokens. Sessions of other users, e.g. on
+    /// a server passing each request's token, are only returned.
+    /// # Example
+    /// ```
+    /// let result = auth_client
+    ///     .update_user_with_session(updated_user, &access_token)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// if let Some(session) = result.new_session {
+    ///     save_refresh_token(&session.refresh_token);
+    /// }
+    /// ```
+    pub async fn update_user_with_session(
+        &self,
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<UpdateUserResult, Error> {
+        let mut headers = header::HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+        );
 
-        let body = serde_json::to_string(&credentials)?;
+        let body = serde_json::to_string::<UpdatedUser>(&updated_user)?;
 
-        let response = self
-            .client
-            .post(format!(
-                "{}{}/token?grant_type=id_token",
-                self.project_url, AUTH_V1
-            ))
+        // Checked before sending, a retry on 401 replaces the stored session's token
+        let owns_stored_session = self
+            .session()
+            .is_some_and(|session| session.access_token == bearer_token);
+
+        let request = self
+            .request(
+                Method::PUT,
+                format!("{}{}/user", self.project_url, self.auth_path),
+            )
             .headers(headers)
-            .body(body)
-            .send()
-            .await?;
+            .body(body);
+
+        let response = self.send_with_bearer(request, bearer_token).await?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
+        if let Ok(session) = from_str::<Session>(&res_body) {
+            if owns_stored_session {
+                self.store_session(&session);
+            }
+            return Ok(UpdateUserResult {
+                user: session.user.clone(),
+                new_session: Some(session),
+            });
+        }
+
+        if let Ok(user) = from_str(&res_body) {
+            return Ok(UpdateUserResult {
+                user,
+                new_session: None,
+            });
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::PUT,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Like [`update_user`](AuthClient::update_user), but returns the untyped JSON body
+    /// # Example
+    /// ```
+    /// let user = auth_client
+    ///     .update_user_raw(updated_user, &access_token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn update_user_raw(
+        &self,
+        updated_user: UpdatedUser,
+        bearer_token: &str,
+    ) -> Result<Value, Error> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+        );
+
+        let body = serde_json::to_string::<UpdatedUser>(&updated_user)?;
+
+        let request = self
+            .request(
+                Method::PUT,
+                format!("{}{}/user", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .body(body);
+
+        let response = self.send_with_bearer(request, bearer_token).await?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            return Ok(from_str(&res_body)?);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::PUT,
+            path: res_path,
+            body: res_body,
         })
     }
 
+    /// Allows signing in with an OIDC ID token. The authentication provider used should be enabled and configured.
+    /// # Example
+    /// ```
+    /// let credentials = IdTokenCredentials {
+    ///     provider: Provider::Github,
+    ///     token: "<id-token-from-auth-provider>",
+    /// };
+    ///
+    /// let session = auth_client
+    ///     .login_with_id_token(credentials)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn login_with_id_token(
+        &self,
+        credentials: IdTokenCredentials,
+    ) -> Result<Session, Error> {
+        self.token_grant("id_token", serde_json::to_value(&credentials)?)
+            .await
+    }
+
     /// Sends an invite link to an email address.
     /// Requires admin permissions to issue invites
     ///
-    /// The data field corresponds to the `raw_user_meta_data` User field
+    /// The data field corresponds to the `raw_user_meta_data` User field. It is sent as the
+    /// top-level `data` of the request, the same shape as sign ups, and stays on the user once
+    /// they accept the invite, so it can pre-seed metadata like a team assignment.
     /// # Example
     /// ```
     /// let demo_email = env::var("DEMO_INVITE").unwrap();
     ///
     /// let user = auth_client
-    ///     .invite_user_by_email(&demo_email, None, auth_client.api_key())
+    ///     .invite_user_by_email(
+    ///         &demo_email,
+    ///         Some(json!({ "team": "engineering" })),
+    ///         auth_client.api_key(),
+    ///     )
     ///     .await
     ///     .unwrap();
     ///```
@@ -776,15 +1175,19 @@ impl AuthClient {
         let body = serde_json::to_string(&invite_payload)?;
 
         let response = self
-            .client
-            .post(format!("{}{}/invite", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/invite", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
         if let Ok(user) = from_str(&res_body) {
             return Ok(user);
@@ -793,493 +1196,2444 @@ impl AuthClient {
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Verify the OTP sent to the user
+    /// Create a user directly, skipping the signup flow.
+    /// Requires the service role key.
+    ///
+    /// Set `email_confirm` or `phone_confirm` to create an already confirmed user.
     /// # Example
     /// ```
-    /// let params = VerifyEmailOtpParams {
-    ///     token: "abc123",
-    ///     otp_type: OtpType::EmailChange,
-    ///     options: None,
+    /// let params = AdminUserAttributes {
+    ///     email: Some(demo_email),
+    ///     password: Some(demo_password),
+    ///     email_confirm: Some(true),
+    ///     ..Default::default()
     /// };
     ///
-    /// let session = auth_client
-    ///     .verify_otp(params)
+    /// let user = auth_client
+    ///     .admin_create_user(params, service_role_key)
     ///     .await
     ///     .unwrap();
-    ///```
-    pub async fn verify_otp(&self, params: VerifyOtpParams) -> Result<Session, Error> {
+    /// ```
+    pub async fn admin_create_user(
+        &self,
+        params: AdminUserAttributes,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
         let body = serde_json::to_string(&params)?;
 
         let response = self
-            .client
-            .post(&format!("{}{}/verify", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/admin/users", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
+        if let Ok(user) = from_str(&res_body) {
+            return Ok(user);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Check the Health Status of the Auth Server
+    /// Update any user by their id. Requires the service role key.
+    ///
+    /// Only the fields that are set are changed.
     /// # Example
     /// ```
-    /// let health = auth_client
-    ///     .get_health()
+    /// let params = AdminUserAttributes {
+    ///     email_confirm: Some(true),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let user = auth_client
+    ///     .admin_update_user_by_id(&user_id, params, service_role_key)
     ///     .await
     ///     .unwrap();
     /// ```
-    pub async fn get_health(&self) -> Result<AuthServerHealth, Error> {
+    pub async fn admin_update_user_by_id(
+        &self,
+        user_id: &str,
+        params: AdminUserAttributes,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
+
+        let body = serde_json::to_string(&params)?;
 
         let response = self
-            .client
-            .get(&format!("{}{}/health", self.project_url, AUTH_V1))
+            .request(
+                Method::PUT,
+                format!(
+                    "{}{}/admin/users/{}",
+                    self.project_url, self.auth_path, user_id
+                ),
+            )
             .headers(headers)
+            .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(health) = from_str::<AuthServerHealth>(&res_body) {
-            return Ok(health);
+        if let Ok(user) = from_str(&res_body) {
+            return Ok(user);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::PUT,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Retrieve the public settings of the server
+    /// Set a single key of a user's `app_metadata`. Requires the service role key.
+    ///
+    /// GoTrue merges the top-level keys of `app_metadata`, so the user's other keys are kept.
+    /// Setting a key to `Value::Null` removes it. Nested objects are replaced, not merged.
     /// # Example
     /// ```
-    /// let settings = auth_client
-    ///     .get_settings()
+    /// let user = auth_client
+    ///     .admin_set_app_metadata(&user_id, "stripe_customer_id", json!("cus_123"), service_role_key)
     ///     .await
     ///     .unwrap();
     /// ```
-    pub async fn get_settings(&self) -> Result<AuthServerSettings, Error> {
+    pub async fn admin_set_app_metadata(
+        &self,
+        user_id: &str,
+        key: &str,
+        value: Value,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            app_metadata: Some(Value::Object(Map::from_iter([(key.to_owned(), value)]))),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+            .await
+    }
+
+    /// Ban a user for the given duration. Requires the service role key.
+    ///
+    /// The returned user's `banned_until` shows when the ban ends. Durations are sent with
+    /// second precision.
+    /// # Example
+    /// ```
+    /// let user = auth_client
+    ///     .admin_ban_user(&user_id, Duration::from_secs(24 * 60 * 60), service_role_key)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// println!("Banned until {}", user.banned_until.unwrap());
+    /// ```
+    pub async fn admin_ban_user(
+        &self,
+        user_id: &str,
+        duration: Duration,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            ban_duration: Some(format_ban_duration(duration)),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+            .await
+    }
+
+    /// Lift a user's ban. Requires the service role key.
+    pub async fn admin_unban_user(
+        &self,
+        user_id: &str,
+        service_role_key: &str,
+    ) -> Result<User, Error> {
+        let params = AdminUserAttributes {
+            ban_duration: Some("none".to_string()),
+            ..Default::default()
+        };
+
+        self.admin_update_user_by_id(user_id, params, service_role_key)
+            .await
+    }
+
+    /// Delete a user. Requires the service role key.
+    /// # Example
+    /// ```
+    /// auth_client
+    ///     .admin_delete_user(&user.id.to_string(), service_role_key)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn admin_delete_user(
+        &self,
+        user_id: &str,
+        service_role_key: &str,
+    ) -> Result<(), Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
         let response = self
-            .client
-            .get(&format!("{}{}/settings", self.project_url, AUTH_V1))
+            .request(
+                Method::DELETE,
+                format!(
+                    "{}{}/admin/users/{}",
+                    self.project_url, self.auth_path, user_id
+                ),
+            )
             .headers(headers)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(settings) = from_str(&res_body) {
-            return Ok(settings);
+        if res_status.is_success() {
+            return Ok(());
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::DELETE,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Exchange refresh token for a new session
+    /// Delete many users, e.g. when tearing down tests, running up to `concurrency` deletions at
+    /// once. Requires the service role key.
+    ///
+    /// `concurrency` is capped at 10 to stay clear of rate limits. Every id is attempted, the
+    /// result of each is returned in the order the deletions finished.
     /// # Example
     /// ```
-    /// // When a user signs in they get a session
-    /// let original_session = auth_client
-    ///     .login_with_email_and_password(demo_email.as_ref(), demo_password)
-    ///     .await
-    ///     .unwrap();
+    /// let results = auth_client
+    ///     .admin_delete_users(&test_user_ids, 5, service_role_key)
+    ///     .await;
     ///
-    /// // Exchange the refresh token from the original session to create a new session
-    /// let new_session = auth_client
-    ///     .refresh_session(original_session.refresh_token)
+    /// for (id, result) in results {
+    ///     if let Err(error) = result {
+    ///         eprintln!("Failed to delete {}: {}", id, error);
+    ///     }
+    /// }
+    /// ```
+    pub async fn admin_delete_users(
+        &self,
+        ids: &[String],
+        concurrency: usize,
+        service_role_key: &str,
+    ) -> Vec<(String, Result<(), Error>)> {
+        const MAX_CONCURRENCY: usize = 10;
+
+        stream::iter(ids)
+            .map(|id| async move {
+                let result = self.admin_delete_user(id, service_role_key).await;
+                (id.clone(), result)
+            })
+            .buffer_unordered(concurrency.clamp(1, MAX_CONCURRENCY))
+            .collect()
+            .await
+    }
+
+    /// List the users of the project, one page at a time.
+    /// Requires the service role key.
+    /// # Example
+    /// ```
+    /// let params = ListUsersParams {
+    ///     page: Some(1),
+    ///     per_page: Some(100),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let page = auth_client
+    ///     .admin_list_users(Some(params), service_role_key)
     ///     .await
     ///     .unwrap();
     /// ```
-    pub async fn exchange_token_for_session(&self, refresh_token: &str) -> Result<Session, Error> {
+    pub async fn admin_list_users(
+        &self,
+        params: Option<ListUsersParams>,
+        service_role_key: &str,
+    ) -> Result<ListUsersResponse, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
-
-        let body = serde_json::to_string(&RefreshSessionPayload { refresh_token })?;
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
         let response = self
-            .client
-            .post(&format!(
-                "{}{}/token?grant_type=refresh_token",
-                self.project_url, AUTH_V1
-            ))
+            .request(
+                Method::GET,
+                format!("{}{}/admin/users", self.project_url, self.auth_path),
+            )
+            .query(&params.unwrap_or_default())
             .headers(headers)
-            .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
+        if let Ok(users) = from_str(&res_body) {
+            return Ok(users);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    pub async fn refresh_session(&self, refresh_token: &str) -> Result<Session, Error> {
-        self.exchange_token_for_session(refresh_token).await
-    }
-
-    /// Exchange code for a new session
+    /// List the SSO providers of the project. Requires the service role key.
     /// # Example
     /// ```
-    /// // When a user signs in they get a session
-    /// let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
-    ///
-    /// let options = LoginWithOAuthOptions {
-    ///     query_params: Some(HashMap::from([
-    ///         (
-    ///             "redirect_to".to_owned(),
-    ///             "http://localhost:3000/auth/callback".to_owned(),
-    ///         ),
-    ///         ("response_type".to_owned(), "code".to_owned()),
-    ///         ("skip_browser_redirect".to_owned(), "true".to_owned()),
-    ///         (
-    ///             "code_challenge".to_owned(),
-    ///             pkce_challenge.as_str().to_owned(),
-    ///         ),
-    ///         ("code_challenge_method".to_owned(), "S256".to_owned()),
-    ///     ])),
-    ///     ..Default::default()
-    /// };
-    ///
-    /// let oauth_res = auth_client
-    ///     .login_with_oauth(Provider::Github, Some(options))?;
-    ///
-    /// // Exchange the code to create a new session
-    /// let new_session = auth_client
-    ///     .exchange_code_for_session(auth_code, pkce_verifier)
+    /// let providers = auth_client
+    ///     .admin_list_sso_providers(service_role_key)
     ///     .await
     ///     .unwrap();
     /// ```
-    pub async fn exchange_code_for_session(
+    pub async fn admin_list_sso_providers(
         &self,
-        auth_code: &str,
-        code_verifier: &str,
-    ) -> Result<Session, Error> {
+        service_role_key: &str,
+    ) -> Result<Vec<SsoProvider>, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
-
-        let body = serde_json::to_string(&ExchangeCodeForSessionPayload {
-            auth_code,
-            code_verifier,
-        })?;
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
         let response = self
-            .client
-            .post(&format!(
-                "{}{}/token?grant_type=pkce",
-                self.project_url, AUTH_V1
-            ))
+            .request(
+                Method::GET,
+                format!("{}{}/admin/sso/providers", self.project_url, self.auth_path),
+            )
             .headers(headers)
-            .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if let Ok(session) = from_str(&res_body) {
-            return Ok(session);
+        if let Ok(providers) = from_str::<SsoProvidersResponse>(&res_body) {
+            return Ok(providers.items);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
         // Fallback: return raw error
-        Err(Error::AuthError {
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Send a password recovery email. Invalid Email addresses will return Error Code 400.
-    /// Valid email addresses that are not registered as users will not return an error.
+    /// Get an SSO provider by its id or resource id. Requires the service role key.
     /// # Example
     /// ```
-    /// let response = auth_client.reset_password_for_email(demo_email, None).await.unwrap();
-    /// ```
-    pub async fn reset_password_for_email(
+    /// let provider = auth_client
+    ///     .admin_get_sso_provider(&provider_id, service_role_key)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn admin_get_sso_provider(
         &self,
-        email: &str,
-        options: Option<ResetPasswordOptions>,
-    ) -> Result<(), Error> {
-        let redirect_to = options
-            .as_ref()
-            .and_then(|o| o.email_redirect_to.as_deref().map(str::to_owned));
-
-        let payload = ResetPasswordForEmailPayload {
-            email: String::from(email),
-            options,
-        };
-
+        provider_id: &str,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
-
-        let body = serde_json::to_string(&payload)?;
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
         let response = self
-            .client
-            .post(&format!("{}{}/recover", self.project_url, AUTH_V1))
-            .query(&[("redirect_to", redirect_to.as_deref())])
+            .request(
+                Method::GET,
+                format!(
+                    "{}{}/admin/sso/providers/{}",
+                    self.project_url, self.auth_path, provider_id
+                ),
+            )
             .headers(headers)
-            .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if res_status.is_success() {
-            return Ok(());
+        if let Ok(provider) = from_str(&res_body) {
+            return Ok(provider);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
-        Err(Error::AuthError {
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Resends emails for existing signup confirmation, email change, SMS OTP, or phone change OTP.
+    /// Add a SAML SSO provider to the project. Requires the service role key.
     /// # Example
     /// ```
-    /// // Resend can also take MobileResendParams
-    /// let credentials = DesktopResendParams {
-    ///     otp_type: supabase_auth::models::EmailOtpType::Email,
-    ///     email: demo_email.to_owned(),
-    ///     options: None,
+    /// let params = CreateSsoProviderParams {
+    ///     metadata_url: Some("https://idp.example.com/saml/metadata".to_string()),
+    ///     domains: vec!["example.com".to_string()],
+    ///     ..Default::default()
     /// };
     ///
-    /// let resend = auth_client.resend(ResendParams::Desktop(credentials)).await;
+    /// let provider = auth_client
+    ///     .admin_create_sso_provider(params, service_role_key)
+    ///     .await
+    ///     .unwrap();
     /// ```
-    pub async fn resend(&self, credentials: ResendParams) -> Result<(), Error> {
+    pub async fn admin_create_sso_provider(
+        &self,
+        params: CreateSsoProviderParams,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
+        );
 
-        let body = serde_json::to_string(&credentials)?;
+        let payload = CreateSsoProviderPayload {
+            provider_type: "saml",
+            params,
+        };
+
+        let body = serde_json::to_string(&payload)?;
 
         let response = self
-            .client
-            .post(&format!("{}{}/resend", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/admin/sso/providers", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if res_status.is_success() {
-            return Ok(());
+        if let Ok(provider) = from_str(&res_body) {
+            return Ok(provider);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
-        Err(Error::AuthError {
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Logs out a user with a given scope
+    /// Remove an SSO provider from the project, returning the removed provider.
+    /// Requires the service role key.
     /// # Example
     /// ```
-    /// auth_client.logout(Some(LogoutScope::Global), session.access_token).await.unwrap();
+    /// auth_client
+    ///     .admin_delete_sso_provider(&provider_id, service_role_key)
+    ///     .await
+    ///     .unwrap();
     /// ```
-    pub async fn logout(
+    pub async fn admin_delete_sso_provider(
         &self,
-        scope: Option<LogoutScope>,
-        bearer_token: &str,
-    ) -> Result<(), Error> {
+        provider_id: &str,
+        service_role_key: &str,
+    ) -> Result<SsoProvider, Error> {
         let mut headers = HeaderMap::new();
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
         headers.insert(
             AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+            HeaderValue::from_str(&format!("Bearer {}", service_role_key))?,
         );
 
-        let body = serde_json::to_string(&scope)?;
-
         let response = self
-            .client
-            .post(&format!("{}{}/logout", self.project_url, AUTH_V1))
+            .request(
+                Method::DELETE,
+                format!(
+                    "{}{}/admin/sso/providers/{}",
+                    self.project_url, self.auth_path, provider_id
+                ),
+            )
             .headers(headers)
-            .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if res_status.is_success() {
-            return Ok(());
+        if let Ok(provider) = from_str(&res_body) {
+            return Ok(provider);
         }
 
         if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
             return Err(Error::AuthError {
                 status: res_status,
+                code: error.error_code,
                 message: error.message,
             });
         }
 
-        Err(Error::AuthError {
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
             status: res_status,
-            message: res_body,
+            method: Method::DELETE,
+            path: res_path,
+            body: res_body,
         })
     }
 
-    /// Initiates an SSO Login Flow
-    /// Returns the URL where the user must authenticate with the SSO Provider
+    /// Stream every user of the project, fetching the following pages as the stream is
+    /// consumed. Requires the service role key.
     ///
-    /// WARNING: Requires an SSO Provider and Supabase Pro plan
+    /// Pages of 100 users are fetched with a short pause in between to stay clear of rate
+    /// limits. The stream ends after the first error.
+    /// # Example
+    /// ```
+    /// let mut users = std::pin::pin!(auth_client.admin_users_stream(service_role_key));
+    ///
+    /// while let Some(user) = users.next().await {
+    ///     println!("{}", user.unwrap().email);
+    /// }
+    /// ```
+    pub fn admin_users_stream<'a>(
+        &'a self,
+        service_role_key: &'a str,
+    ) -> impl Stream<Item = Result<User, Error>> + 'a {
+        const PER_PAGE: u32 = 100;
+        const PAGE_DELAY: Duration = Duration::from_millis(100);
+
+        stream::try_unfold(Some(1), move |page| async move {
+            let Some(page) = page else {
+                return Ok::<_, Error>(None);
+            };
+
+            if page > 1 {
+                tokio::time::sleep(PAGE_DELAY).await;
+            }
+
+            let params = ListUsersParams {
+                page: Some(page),
+                per_page: Some(PER_PAGE),
+                ..Default::default()
+            };
+
+            let users = self
+                .admin_list_users(Some(params), service_role_key)
+                .await?
+                .users;
+
+            let next_page = (users.len() >= PER_PAGE as usize).then_some(page + 1);
+
+            Ok(Some((stream::iter(users.into_iter().map(Ok)), next_page)))
+        })
+        .try_flatten()
+    }
+
+    /// Find a user by their email address, ignoring case. Returns `None` if no user matches.
+    /// Requires the service role key.
     ///
+    /// GoTrue has no direct lookup by email, so this lists users filtered by the email and pages
+    /// through the results until an exact match is found. That is usually a single request, but
+    /// each additional page of partial matches costs another round trip.
     /// # Example
     /// ```
-    /// let url = auth_client.sso(params).await.unwrap();
+    /// let user = auth_client
+    ///     .admin_get_user_by_email("demo@demo.com", service_role_key)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn admin_get_user_by_email(
+        &self,
+        email: &str,
+        service_role_key: &str,
+    ) -> Result<Option<User>, Error> {
+        const PER_PAGE: u32 = 100;
+
+        let mut page = 1;
+
+        loop {
+            let params = ListUsersParams {
+                page: Some(page),
+                per_page: Some(PER_PAGE),
+                filter: Some(email.to_string()),
+            };
+
+            let users = self
+                .admin_list_users(Some(params), service_role_key)
+                .await?
+                .users;
+
+            let count = users.len();
+
+            if let Some(user) = users
+                .into_iter()
+                .find(|user| user.email.eq_ignore_ascii_case(email))
+            {
+                return Ok(Some(user));
+            }
+
+            if count < PER_PAGE as usize {
+                return Ok(None);
+            }
+
+            page += 1;
+        }
+    }
+
+    /// Verify the OTP sent to the user, storing the returned session like the login methods
+    /// # Example
+    /// ```
+    /// let params = VerifyOtpParams::email(demo_email, "123456", EmailOtpType::Signup);
     ///
-    /// println!("{}", url.to_string());
+    /// let session = auth_client
+    ///     .verify_otp(params)
+    ///     .await
+    ///     .unwrap();
+    ///```
+    pub async fn verify_otp(&self, params: VerifyOtpParams) -> Result<Session, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+
+        let body = serde_json::to_string(&params)?;
+
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}{}/verify", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if let Ok(session) = from_str::<Session>(&res_body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Confirm a new email address with the token sent by [`update_user`](AuthClient::update_user).
+    ///
+    /// Changing the email with `update_user` sends a token to the new address and, with secure
+    /// email change enabled (the default), another one to the current address. Both have to be
+    /// verified: the first call returns [`Error::EmailChangePending`], the second one the new
+    /// session, which is stored like the login methods do.
+    /// # Example
     /// ```
-    pub async fn sso(&self, params: LoginWithSSO) -> Result<Url, Error> {
+    /// let session = auth_client
+    ///     .verify_email_change(new_email, &token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn verify_email_change(&self, email: &str, token: &str) -> Result<Session, Error> {
         let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
 
-        let body = serde_json::to_string::<crate::models::LoginWithSSO>(&params)?;
+        let params = VerifyOtpParams::email(email, token, EmailOtpType::EmailChange);
+        let body = serde_json::to_string(&params)?;
 
         let response = self
-            .client
-            .post(&format!("{}{}/sso", self.project_url, AUTH_V1))
+            .request(
+                Method::POST,
+                format!("{}{}/verify", self.project_url, self.auth_path),
+            )
             .headers(headers)
             .body(body)
             .send()
-            .await?;
+            .await
+            .map_err(|error| self.transport_error(error))?;
 
         let res_status = response.status();
-        let url = response.url().clone();
-        let res_body = response.text().await?;
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
 
-        if res_status.is_server_error() || res_status.is_client_error() {
-            if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
-                return Err(AuthError {
-                    status: res_status,
-                    message: error.message,
-                });
+        if res_status.is_success() {
+            match from_str::<EmailChangeResponse>(&res_body) {
+                Ok(EmailChangeResponse::Session(session)) => {
+                    self.store_session(&session);
+                    return Ok(session);
+                }
+                Ok(EmailChangeResponse::Pending { .. }) => return Err(Error::EmailChangePending),
+                Err(_) => {}
             }
+        } else if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
 
-            // Fallback: return raw error
-            return Err(AuthError {
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Check the Health Status of the Auth Server
+    /// # Example
+    /// ```
+    /// let health = auth_client
+    ///     .get_health()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn get_health(&self) -> Result<AuthServerHealth, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+
+        let response = self
+            .request(
+                Method::GET,
+                format!("{}{}/health", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if let Ok(health) = from_str::<AuthServerHealth>(&res_body) {
+            return Ok(health);
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
                 status: res_status,
-                message: res_body,
+                code: error.error_code,
+                message: error.message,
             });
         }
 
-        Ok(url)
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
+        })
     }
 
-    /// Get the project URL from an AuthClient
-    pub fn project_url(&self) -> &str {
-        &self.project_url
+    /// Check the Health Status of the Auth Server, failing once `timeout` passes.
+    ///
+    /// Meant for readiness probes, the request is sent once and never retried.
+    /// # Example
+    /// ```
+    /// let health = auth_client
+    ///     .get_health_with(Duration::from_secs(2))
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn get_health_with(&self, timeout: Duration) -> Result<AuthServerHealth, Error> {
+        self.with_timeout(timeout).get_health().await
     }
 
-    /// Get the API Key from an AuthClient
-    pub fn api_key(&self) -> &str {
-        &self.api_key
+    /// Returns true if the Auth Server answers its health check within `timeout`
+    /// # Example
+    /// ```
+    /// if !auth_client.is_healthy(Duration::from_secs(2)).await {
+    ///     return StatusCode::SERVICE_UNAVAILABLE;
+    /// }
+    /// ```
+    pub async fn is_healthy(&self, timeout: Duration) -> bool {
+        self.get_health_with(timeout).await.is_ok()
     }
 
-    /// Get the JWT Secret from an AuthClient
-    pub fn jwt_secret(&self) -> &str {
-        &self.jwt_secret
+    /// Open a connection to the Auth Server ahead of the first real request, so that request
+    /// doesn't pay for the TLS handshake.
+    ///
+    /// This is optional and best effort, the connection is reopened on demand anyway, so errors
+    /// can be ignored.
+    /// # Example
+    /// ```
+    /// let _ = auth_client.warmup().await;
+    /// ```
+    pub async fn warmup(&self) -> Result<(), Error> {
+        self.get_health().await.map(|_| ())
+    }
+
+    /// Retrieve the public settings of the server
+    ///
+    /// Settings are cached, calls within the TTL set with [`AuthClientBuilder::settings_ttl`]
+    /// reuse the last result. Use [`get_settings_uncached`](AuthClient::get_settings_uncached)
+    /// to force a refresh.
+    /// # Example
+    /// ```
+    /// let settings = auth_client
+    ///     .get_settings()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn get_settings(&self) -> Result<AuthServerSettings, Error> {
+        if let Ok(cache) = self.settings_cache.read() {
+            if let Some((fetched_at, settings)) = cache.as_ref() {
+                if fetched_at.elapsed() < self.settings_ttl {
+                    return Ok(settings.clone());
+                }
+            }
+        }
+
+        self.get_settings_uncached().await
+    }
+
+    /// Whether new email signups have to confirm their email before they get a session
+    ///
+    /// False when the server auto-confirms emails or signups are disabled. Read from the cached
+    /// [`get_settings`](AuthClient::get_settings).
+    /// # Example
+    /// ```
+    /// if auth_client.email_confirmation_required().await.unwrap() {
+    ///     show_check_your_email();
+    /// }
+    /// ```
+    pub async fn email_confirmation_required(&self) -> Result<bool, Error> {
+        let settings = self.get_settings().await?;
+
+        Ok(!settings.disable_signup && !settings.mailer_autoconfirm)
+    }
+
+    /// The password requirements of the server, e.g. to validate passwords while they are typed.
+    /// Read from the cached [`get_settings`](AuthClient::get_settings).
+    ///
+    /// Servers that don't report their requirements in the settings get GoTrue's defaults, so
+    /// the server can still reject a password this policy accepts.
+    /// # Example
+    /// ```
+    /// let policy = auth_client.password_policy().await.unwrap();
+    ///
+    /// if let Err(problems) = policy.validate(&password) {
+    ///     show_hints(&problems);
+    /// }
+    /// ```
+    pub async fn password_policy(&self) -> Result<PasswordPolicy, Error> {
+        let settings = self.get_settings().await?;
+
+        Ok(password_policy(&settings))
+    }
+
+    /// Retrieve the public settings of the server, bypassing and refreshing the cache
+    /// # Example
+    /// ```
+    /// let settings = auth_client
+    ///     .get_settings_uncached()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn get_settings_uncached(&self) -> Result<AuthServerSettings, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+
+        let response = self
+            .request(
+                Method::GET,
+                format!("{}{}/settings", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if let Ok(settings) = from_str::<AuthServerSettings>(&res_body) {
+            if let Ok(mut cache) = self.settings_cache.write() {
+                *cache = Some((Instant::now(), settings.clone()));
+            }
+
+            return Ok(settings);
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Retrieve the public settings of the server as untyped JSON. These are never cached.
+    /// # Example
+    /// ```
+    /// let settings = auth_client.get_settings_raw().await.unwrap();
+    ///
+    /// let new_setting = &settings["new_setting"];
+    /// ```
+    pub async fn get_settings_raw(&self) -> Result<Value, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+
+        let response = self
+            .request(
+                Method::GET,
+                format!("{}{}/settings", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            return Ok(from_str(&res_body)?);
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Request a session from the `/token` endpoint with any `grant_type`, e.g. one a custom or
+    /// newer GoTrue supports that this client has no method for. `body` is sent as is and the
+    /// returned session is stored like the login methods do.
+    /// # Example
+    /// ```
+    /// let session = auth_client
+    ///     .token_grant("password", json!({ "email": demo_email, "password": demo_password }))
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn token_grant(&self, grant_type: &str, body: Value) -> Result<Session, Error> {
+        self.token_grant_with_headers(grant_type, body, HeaderMap::new())
+            .await
+    }
+
+    /// [`token_grant`](AuthClient::token_grant), sending `extra_headers` along with the
+    /// client's own headers, which take precedence
+    async fn token_grant_with_headers(
+        &self,
+        grant_type: &str,
+        body: Value,
+        extra_headers: HeaderMap,
+    ) -> Result<Session, Error> {
+        let mut headers = extra_headers;
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+
+        let body = serde_json::to_string(&body)?;
+
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}{}/token", self.project_url, self.auth_path),
+            )
+            .query(&[("grant_type", grant_type)])
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if let Ok(session) = from_str::<Session>(&res_body) {
+            self.store_session(&session);
+            return Ok(session);
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        // Fallback: return raw error
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Exchange refresh token for a new session
+    /// # Example
+    /// ```
+    /// // When a user signs in they get a session
+    /// let original_session = auth_client
+    ///     .login_with_email_and_password(demo_email.as_ref(), demo_password)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// // Exchange the refresh token from the original session to create a new session
+    /// let new_session = auth_client
+    ///     .refresh_session(original_session.refresh_token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn exchange_token_for_session(&self, refresh_token: &str) -> Result<Session, Error> {
+        self.token_grant(
+            "refresh_token",
+            serde_json::to_value(RefreshSessionPayload { refresh_token })?,
+        )
+        .await
+    }
+
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<Session, Error> {
+        self.exchange_token_for_session(refresh_token).await
+    }
+
+    /// Refresh the stored session with its own refresh token, storing and returning the new
+    /// session. Unlike [`get_session`](AuthClient::get_session) it always refreshes.
+    ///
+    /// Concurrent calls share a single refresh. Returns [`Error::NotAuthenticated`] if there is
+    /// no stored session.
+    /// # Example
+    /// ```
+    /// // Pick up changed claims, e.g. after the user's role was updated
+    /// let session = auth_client.refresh_current_session().await.unwrap();
+    /// ```
+    pub async fn refresh_current_session(&self) -> Result<Session, Error> {
+        let session = self.stored_session()?;
+
+        self.refresh_stored_session(&session).await
+    }
+
+    /// Bootstrap an empty client from a persisted refresh token, e.g. on app start.
+    ///
+    /// The refresh token is exchanged for a new session, which is stored and returned. A revoked,
+    /// already used, or expired refresh token returns [`Error::InvalidRefreshToken`].
+    /// # Example
+    /// ```
+    /// let session = auth_client
+    ///     .restore_from_refresh_token(&stored_refresh_token)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert!(auth_client.is_authenticated());
+    /// ```
+    pub async fn restore_from_refresh_token(&self, refresh_token: &str) -> Result<Session, Error> {
+        match self.exchange_token_for_session(refresh_token).await {
+            Err(Error::AuthError { code, message, .. })
+                if is_invalid_refresh_token(code.as_deref(), &message) =>
+            {
+                Err(Error::InvalidRefreshToken)
+            }
+            result => result,
+        }
+    }
+
+    /// Exchange code for a new session
+    /// # Example
+    /// ```
+    /// // When a user signs in they get a session
+    /// let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    ///
+    /// let options = LoginWithOAuthOptions {
+    ///     query_params: Some(HashMap::from([
+    ///         (
+    ///             "redirect_to".to_owned(),
+    ///             "http://localhost:3000/auth/callback".to_owned(),
+    ///         ),
+    ///         ("response_type".to_owned(), "code".to_owned()),
+    ///         ("skip_browser_redirect".to_owned(), "true".to_owned()),
+    ///         (
+    ///             "code_challenge".to_owned(),
+    ///             pkce_challenge.as_str().to_owned(),
+    ///         ),
+    ///         ("code_challenge_method".to_owned(), "S256".to_owned()),
+    ///     ])),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let oauth_res = auth_client
+    ///     .login_with_oauth(Provider::Github, Some(options))?;
+    ///
+    /// // Exchange the code to create a new session
+    /// let new_session = auth_client
+    ///     .exchange_code_for_session(auth_code, pkce_verifier)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn exchange_code_for_session(
+        &self,
+        auth_code: &str,
+        code_verifier: &str,
+    ) -> Result<Session, Error> {
+        self.token_grant(
+            "pkce",
+            serde_json::to_value(ExchangeCodeForSessionPayload {
+                auth_code,
+                code_verifier,
+            })?,
+        )
+        .await
+    }
+
+    /// Exchange the `code` of a PKCE callback URL for a session, e.g. in the callback handler of
+    /// a server-side app. The session is stored and returned.
+    ///
+    /// GoTrue only sends a `code` in the PKCE flow, so the verifier created when the flow was
+    /// started is still needed, keep it in a cookie until the callback. Errors GoTrue put in the
+    /// callback URL, like an expired link, are returned as [`Error::AuthError`], and a URL with
+    /// neither returns [`Error::ParseUrlError`]. Email links with a `token_hash` instead of a
+    /// `code` are exchanged with [`verify_otp`](AuthClient::verify_otp).
+    /// # Example
+    /// ```
+    /// // In the handler of http://localhost:3000/auth/callback?code=...
+    /// let session = auth_client
+    ///     .exchange_code_from_url(&request_url, &code_verifier_from_cookie)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn exchange_code_from_url(
+        &self,
+        callback_url: &str,
+        code_verifier: &str,
+    ) -> Result<Session, Error> {
+        let code = code_from_callback_url(callback_url)?;
+
+        self.exchange_code_for_session(&code, code_verifier).await
+    }
+
+    /// Send a password recovery email. Invalid Email addresses will return Error Code 400.
+    /// Valid email addresses that are not registered as users will not return an error.
+    ///
+    /// Returns [`Error::RateLimited`] when too many emails were sent, with the time to wait if the
+    /// server reported it. The OTP and magic link methods do the same.
+    ///
+    /// To complete the recovery, verify the `token_hash` of the link the user clicked with
+    /// [`verify_otp`](AuthClient::verify_otp), which stores a session, then set the new password
+    /// with [`update_user`](AuthClient::update_user). Signing in through the link counts as a
+    /// recent sign in, so no [`reauthenticate`](AuthClient::reauthenticate) nonce is needed.
+    /// # Example
+    /// ```
+    /// auth_client.reset_password_for_email(demo_email, None).await.unwrap();
+    ///
+    /// // In the handler of the link, e.g. /auth/confirm?token_hash=...&type=recovery
+    /// let session = auth_client
+    ///     .verify_otp(VerifyOtpParams::TokenHash(VerifyTokenHashParams {
+    ///         token_hash,
+    ///         otp_type: OtpType::Recovery,
+    ///     }))
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let updated_user = UpdatedUser {
+    ///     password: Some(new_password),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// auth_client
+    ///     .update_user(updated_user, &session.access_token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn reset_password_for_email(
+        &self,
+        email: &str,
+        options: Option<ResetPasswordOptions>,
+    ) -> Result<(), Error> {
+        let redirect_to = options
+            .as_ref()
+            .and_then(|o| o.email_redirect_to.as_deref().map(str::to_owned));
+
+        let payload = ResetPasswordForEmailPayload {
+            email: String::from(email),
+            options,
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+
+        let body = serde_json::to_string(&payload)?;
+
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}{}/recover", self.project_url, self.auth_path),
+            )
+            .query(&[("redirect_to", redirect_to.as_deref())])
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+
+        if res_status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            return Ok(());
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            let message = with_redirect_to(
+                error.message,
+                error.error_code.as_deref(),
+                redirect_to.as_deref(),
+            );
+
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message,
+            });
+        }
+
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Send the signed in user a nonce to confirm a password change with, by email or SMS.
+    ///
+    /// Only needed when "Secure password change" is enabled and the user didn't sign in
+    /// recently. Pass the nonce as [`UpdatedUser::nonce`].
+    /// # Example
+    /// ```
+    /// auth_client.reauthenticate(&session.access_token).await.unwrap();
+    ///
+    /// let updated_user = UpdatedUser {
+    ///     password: Some(new_password),
+    ///     nonce: Some(nonce_from_email),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub async fn reauthenticate(&self, bearer_token: &str) -> Result<(), Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+        );
+
+        let request = self
+            .request(
+                Method::GET,
+                format!("{}{}/reauthenticate", self.project_url, self.auth_path),
+            )
+            .headers(headers);
+
+        let response = self.send_with_bearer(request, bearer_token).await?;
+
+        let res_status = response.status();
+
+        if res_status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            return Ok(());
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::GET,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Resends emails for existing signup confirmation, email change, SMS OTP, or phone change OTP.
+    /// # Example
+    /// ```
+    /// // Resend can also take MobileResendParams
+    /// let credentials = DesktopResendParams {
+    ///     otp_type: supabase_auth::models::EmailOtpType::Email,
+    ///     email: demo_email.to_owned(),
+    ///     options: None,
+    /// };
+    ///
+    /// let resend = auth_client.resend(ResendParams::Desktop(credentials)).await;
+    /// ```
+    pub async fn resend(&self, credentials: ResendParams) -> Result<(), Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/json")?);
+
+        let body = serde_json::to_string(&credentials)?;
+
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}{}/resend", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            return Ok(());
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Resends the signup confirmation email, e.g. when the user didn't receive it after
+    /// [`sign_up_with_email_and_password`](AuthClient::sign_up_with_email_and_password) returned an
+    /// [`EmailSignUpResult::ConfirmationResult`]
+    /// # Example
+    /// ```
+    /// auth_client.resend_signup_confirmation(demo_email).await.unwrap();
+    /// ```
+    pub async fn resend_signup_confirmation(&self, email: &str) -> Result<(), Error> {
+        self.resend(ResendParams {
+            otp_type: OtpType::Signup,
+            email: email.to_owned(),
+            options: None,
+        })
+        .await
+    }
+
+    /// Logs out a user with a given scope
+    ///
+    /// - [`LogoutScope::Global`], the default, revokes every session of the user, on all devices.
+    /// - [`LogoutScope::Local`] only revokes the session the access token belongs to.
+    /// - [`LogoutScope::Others`] revokes every session except the current one, which stays valid.
+    ///
+    /// The stored session is cleared unless the scope is `Others`.
+    /// # Example
+    /// ```
+    /// // Sign out everywhere else, staying signed in here
+    /// auth_client.logout(Some(LogoutScope::Others), &session.access_token).await.unwrap();
+    ///
+    /// // Sign out of this device only
+    /// auth_client.logout(Some(LogoutScope::Local), &session.access_token).await.unwrap();
+    /// ```
+    pub async fn logout(
+        &self,
+        scope: Option<LogoutScope>,
+        bearer_token: &str,
+    ) -> Result<(), Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", bearer_token))?,
+        );
+
+        let scope = scope.unwrap_or_default();
+
+        let request = self
+            .request(
+                Method::POST,
+                format!("{}{}/logout", self.project_url, self.auth_path),
+            )
+            .query(&[("scope", &scope)])
+            .headers(headers);
+
+        let response = self.send_with_bearer(request, bearer_token).await?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_success() {
+            if scope != LogoutScope::Others {
+                self.clear_session();
+            }
+            return Ok(());
+        }
+
+        if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+            return Err(Error::AuthError {
+                status: res_status,
+                code: error.error_code,
+                message: error.message,
+            });
+        }
+
+        Err(Error::UnexpectedResponse {
+            status: res_status,
+            method: Method::POST,
+            path: res_path,
+            body: res_body,
+        })
+    }
+
+    /// Sign out of all devices: revokes every session of the stored user, including refresh
+    /// tokens, and clears the stored session.
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    /// # Example
+    /// ```
+    /// auth_client.revoke_all_sessions().await.unwrap();
+    ///
+    /// assert!(!auth_client.is_authenticated());
+    /// ```
+    pub async fn revoke_all_sessions(&self) -> Result<(), Error> {
+        let session = self.stored_session()?;
+
+        self.logout(Some(LogoutScope::Global), &session.access_token)
+            .await
+    }
+
+    /// Initiates an SSO Login Flow
+    /// Returns the URL where the user must authenticate with the SSO Provider
+    ///
+    /// WARNING: Requires an SSO Provider and Supabase Pro plan
+    ///
+    /// # Example
+    /// ```
+    /// let params = LoginWithSSO {
+    ///     domain: Some("example.com".to_string()),
+    ///     redirect_to: Some("https://app.example.com/projects/42".to_string()),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let url = auth_client.sso(params).await.unwrap();
+    ///
+    /// println!("{}", url.to_string());
+    /// ```
+    pub async fn sso(&self, params: LoginWithSSO) -> Result<Url, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+
+        let body = serde_json::to_string(&LoginWithSSOPayload::from(&params))?;
+
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}{}/sso", self.project_url, self.auth_path),
+            )
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        let res_status = response.status();
+        let res_path = response.url().path().to_owned();
+        let url = response.url().clone();
+        let res_body = self.read_body(response).await?;
+
+        if res_status.is_server_error() || res_status.is_client_error() {
+            if let Ok(error) = from_str::<SupabaseHTTPError>(&res_body) {
+                return Err(AuthError {
+                    status: res_status,
+                    code: error.error_code,
+                    message: error.message,
+                });
+            }
+
+            // Fallback: return raw error
+            return Err(Error::UnexpectedResponse {
+                status: res_status,
+                method: Method::POST,
+                path: res_path,
+                body: res_body,
+            });
+        }
+
+        Ok(url)
+    }
+
+    /// Get the session stored by the last successful login, sign up, or refresh
+    /// # Example
+    /// ```
+    /// auth_client.login_with_email(demo_email, demo_password).await.unwrap();
+    ///
+    /// let session = auth_client.session().unwrap();
+    /// ```
+    pub fn session(&self) -> Option<Session> {
+        self.session.borrow().clone()
+    }
+
+    /// Watch the stored session, e.g. to drive a Leptos or Dioxus signal. The receiver holds the
+    /// current session and is notified whenever a login, refresh, or logout changes it.
+    /// # Example
+    /// ```
+    /// let mut session_rx = auth_client.session_watch();
+    ///
+    /// tokio::spawn(async move {
+    ///     while session_rx.changed().await.is_ok() {
+    ///         let signed_in = session_rx.borrow_and_update().is_some();
+    ///         set_signed_in.set(signed_in);
+    ///     }
+    /// });
+    /// ```
+    pub fn session_watch(&self) -> tokio::sync::watch::Receiver<Option<Session>> {
+        self.session.subscribe()
+    }
+
+    /// Seed the client with tokens obtained elsewhere, e.g. restored from a cookie.
+    ///
+    /// If the access token is still valid the user is fetched from the server to build the
+    /// session, otherwise the session is refreshed with the refresh token. The session is
+    /// stored and returned.
+    /// # Example
+    /// ```
+    /// let session = auth_client
+    ///     .set_session(&access_token, &refresh_token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn set_session(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+    ) -> Result<Session, Error> {
+        let claims = decode_jwt_unverified(access_token)?;
+
+        let now = self.unix_now();
+
+        if claims.exp <= now {
+            return self.refresh_session(refresh_token).await;
+        }
+
+        let user = self.get_user(access_token).await?;
+
+        let session = Session {
+            access_token: access_token.to_string(),
+            token_type: "bearer".to_string(),
+            expires_in: (claims.exp - now) as i64,
+            expires_at: claims.exp,
+            refresh_token: refresh_token.to_string(),
+            user,
+            ..Default::default()
+        };
+
+        self.store_session(&session);
+
+        Ok(session)
+    }
+
+    /// Store a session you already have, e.g. one deserialized from your own storage.
+    /// The session is stored as-is, without contacting the server. Does nothing if the client
+    /// was built with [`persist_session(false)`](AuthClientBuilder::persist_session).
+    pub fn set_session_from_struct(&self, session: Session) {
+        self.store_session(&session);
+    }
+
+    /// Snapshot the stored session, e.g. to persist it across restarts. Restore it with
+    /// [`import_session`](AuthClient::import_session).
+    /// # Example
+    /// ```
+    /// if let Some(session) = auth_client.export_session() {
+    ///     std::fs::write("session.json", serde_json::to_string(&session)?)?;
+    /// }
+    /// ```
+    pub fn export_session(&self) -> Option<Session> {
+        self.session()
+    }
+
+    /// Restore a session exported with [`export_session`](AuthClient::export_session). The
+    /// session is stored as-is, an expired one is refreshed by the next
+    /// [`get_session`](AuthClient::get_session).
+    /// # Example
+    /// ```
+    /// let session: Session = serde_json::from_str(&std::fs::read_to_string("session.json")?)?;
+    ///
+    /// auth_client.import_session(session);
+    /// ```
+    pub fn import_session(&self, session: Session) {
+        self.store_session(&session);
+    }
+
+    /// Returns true if the client holds a session
+    pub fn is_authenticated(&self) -> bool {
+        self.session().is_some()
+    }
+
+    /// Returns true if the stored session's access token has expired, by the client's
+    /// [`Clock`](crate::clock::Clock).
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    pub fn session_expired(&self) -> Result<bool, Error> {
+        self.session_expires_within(Duration::ZERO)
+    }
+
+    /// Returns true if the stored session's access token expires within `margin`, by the
+    /// client's [`Clock`](crate::clock::Clock).
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    /// # Example
+    /// ```
+    /// if auth_client.session_expires_within(Duration::from_secs(60)).unwrap() {
+    ///     auth_client.get_session().await.unwrap();
+    /// }
+    /// ```
+    pub fn session_expires_within(&self, margin: Duration) -> Result<bool, Error> {
+        let session = self.stored_session()?;
+
+        Ok(session.expires_within_at(margin, self.clock.now()))
+    }
+
+    /// Headers authenticating requests to other Supabase APIs, like PostgREST or Storage, as the
+    /// signed in user: the `apikey` and an `Authorization: Bearer` with the stored access token.
+    ///
+    /// The stored token is used as-is, call [`get_session`](AuthClient::get_session) first to
+    /// refresh it if needed. Returns [`Error::NotAuthenticated`] if there is no session.
+    /// # Example
+    /// ```
+    /// let headers = auth_client.auth_headers().unwrap();
+    ///
+    /// let todos = reqwest::Client::new()
+    ///     .get(format!("{}/rest/v1/todos", project_url))
+    ///     .headers(headers)
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub fn auth_headers(&self) -> Result<HeaderMap, Error> {
+        let session = self.stored_session()?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("apikey", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", session.access_token))?,
+        );
+
+        Ok(headers)
+    }
+
+    /// Read the session from the `sb-access-token` and `sb-refresh-token` cookies of a request,
+    /// e.g. in an axum or actix-web handler. Returns `None` if either cookie is missing.
+    ///
+    /// The access token is verified locally with the JWT secret and the user is built from its
+    /// claims, so fields that aren't in the token, like `identities`, are empty. Fetch the user
+    /// with [`get_user`](AuthClient::get_user) if you need them. An expired access token returns
+    /// [`Error::WrongToken`], refresh it with the refresh token from the cookies. The session
+    /// isn't stored.
+    /// # Example
+    /// ```
+    /// async fn handler(State(auth_client): State<AuthClient>, headers: HeaderMap) -> Response {
+    ///     match auth_client.session_from_cookies(&headers) {
+    ///         Ok(Some(session)) => render_dashboard(&session.user),
+    ///         _ => Redirect::to("/login").into_response(),
+    ///     }
+    /// }
+    /// ```
+    pub fn session_from_cookies(&self, cookies: &HeaderMap) -> Result<Option<Session>, Error> {
+        session_from_cookies(cookies, &self.jwt_secret, self.unix_now())
+    }
+
+    /// `Set-Cookie` headers storing `session` in the `sb-access-token` and `sb-refresh-token`
+    /// cookies, read back by [`session_from_cookies`](AuthClient::session_from_cookies).
+    ///
+    /// Both cookies are `HttpOnly`, `Secure`, and `SameSite=Lax`. The access token cookie
+    /// expires with the token, the refresh token one after 400 days, the longest browsers allow.
+    /// # Example
+    /// ```
+    /// let session = auth_client.login_with_email(&email, &password).await?;
+    ///
+    /// let mut response = Redirect::to("/dashboard").into_response();
+    /// for (name, value) in auth_client.session_to_set_cookie_headers(&session) {
+    ///     response.headers_mut().append(name, value);
+    /// }
+    /// ```
+    pub fn session_to_set_cookie_headers(
+        &self,
+        session: &Session,
+    ) -> Vec<(HeaderName, HeaderValue)> {
+        session_to_set_cookie_headers(session, self.unix_now())
+    }
+
+    /// Returns true if the stored session has to be stepped up to `aal2`, because the user has
+    /// a verified MFA factor but the session was only authenticated with one factor.
+    ///
+    /// The user's factors are fetched from the server, so factors enrolled or verified since
+    /// signing in are taken into account, and the session's level is read from its access token
+    /// after verifying it with the JWT secret. The stored session is refreshed first if it
+    /// expires within the refresh margin. Returns [`Error::NotAuthenticated`] if there is no
+    /// stored session.
+    /// # Example
+    /// ```
+    /// if auth_client.needs_mfa().await.unwrap() {
+    ///     show_two_factor_prompt();
+    /// }
+    /// ```
+    pub async fn needs_mfa(&self) -> Result<bool, Error> {
+        let session = self.get_session().await?;
+        let user = self.get_user(&session.access_token).await?;
+
+        // The request may have refreshed the session, e.g. with `retry_on_401`
+        let session = self.session().unwrap_or(session);
+
+        let current_level = session
+            .authenticator_assurance_level(&DecodingKey::from_secret(self.jwt_secret.as_bytes()))?
+            .unwrap_or(AuthenticatorAssuranceLevel::Aal1);
+
+        let next_level = if user.factors.iter().any(Factor::is_verified) {
+            AuthenticatorAssuranceLevel::Aal2
+        } else {
+            current_level
+        };
+
+        Ok(current_level < next_level)
+    }
+
+    /// Get the stored session, refreshing it first if it expires within the refresh margin.
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    /// # Example
+    /// ```
+    /// let session = auth_client.get_session().await.unwrap();
+    ///
+    /// let user = auth_client.get_user(&session.access_token).await.unwrap();
+    /// ```
+    pub async fn get_session(&self) -> Result<Session, Error> {
+        let session = self.stored_session()?;
+
+        if !session.expires_within_at(
+            effective_refresh_margin(self.refresh_margin, &session),
+            self.clock.now(),
+        ) {
+            return Ok(session);
+        }
+
+        self.refresh_stored_session(&session).await
+    }
+
+    /// Wait until the user of the stored session has confirmed their email, polling every
+    /// `poll_interval`, and return the confirmed session. Returns [`Error::NotAuthenticated`]
+    /// right away if there is no stored session, and [`Error::ConfirmationTimeout`] if the
+    /// email isn't confirmed within `timeout`.
+    ///
+    /// Each poll fetches the user from the server, and the session is refreshed as soon as the
+    /// email is confirmed. This needs a session whose email is still unconfirmed, e.g. of an
+    /// anonymous user that added an email with [`update_user`](AuthClient::update_user). Signing
+    /// up with email confirmation enabled doesn't return a session, so there is nothing to poll
+    /// after [`sign_up_with_email_and_password`](AuthClient::sign_up_with_email_and_password).
+    /// Failures to reach the server are retried on the next poll, other errors are returned.
+    /// # Example
+    /// ```
+    /// show_waiting_for_confirmation_screen();
+    ///
+    /// let session = auth_client
+    ///     .wait_for_confirmation(Duration::from_secs(600), Duration::from_secs(5))
+    ///     .await?;
+    /// ```
+    pub async fn wait_for_confirmation(
+        &self,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<Session, Error> {
+        // Nothing to poll without a session
+        self.stored_session()?;
+
+        let poll = async {
+            loop {
+                let session = match self.get_session().await {
+                    Ok(session) => session,
+                    Err(Error::Transport { .. }) => {
+                        tokio::time::sleep(poll_interval).await;
+                        continue;
+                    }
+                    Err(error) => return Err(error),
+                };
+
+                if session.user.has_confirmed_email() {
+                    return Ok(session);
+                }
+
+                match self.get_user(&session.access_token).await {
+                    Ok(user) if user.has_confirmed_email() => {
+                        return self.refresh_stored_session(&session).await;
+                    }
+                    Ok(_) | Err(Error::Transport { .. }) => {}
+                    Err(error) => return Err(error),
+                }
+
+                tokio::time::sleep(poll_interval).await;
+            }
+        };
+
+        tokio::time::timeout(timeout, poll)
+            .await
+            .map_err(|_| Error::ConfirmationTimeout)?
+    }
+
+    /// Spawn a task on the current tokio runtime that refreshes the stored session shortly
+    /// before it expires, for as long as the client holds a session.
+    ///
+    /// The task stops once the session is cleared, e.g. by [`AuthClient::logout`]. Abort the
+    /// returned handle to stop it earlier.
+    ///
+    /// Refreshes that fail because the server can't be reached, errors, or rate limits the
+    /// client are retried with backoff, see
+    /// [`refresh_backoff`](AuthClientBuilder::refresh_backoff). A revoked or already used
+    /// refresh token clears the session and stops the task, and so does any other error,
+    /// without clearing the session.
+    /// # Example
+    /// ```
+    /// auth_client.login_with_email(demo_email, demo_password).await.unwrap();
+    ///
+    /// let handle = auth_client.start_auto_refresh();
+    /// ```
+    pub fn start_auto_refresh(&self) -> JoinHandle<()> {
+        let client = self.clone();
+
+        tokio::spawn(async move {
+            let mut failures = 0;
+
+            while let Some(session) = client.session() {
+                let refresh_at = session.expires_at.saturating_sub(
+                    effective_refresh_margin(client.refresh_margin, &session).as_secs(),
+                );
+                let now = client.unix_now();
+
+                tokio::time::sleep(Duration::from_secs(refresh_at.saturating_sub(now))).await;
+
+                // The session may have been replaced or cleared while sleeping
+                match client.session() {
+                    Some(current) if current.refresh_token == session.refresh_token => {
+                        match client.refresh_stored_session(&current).await {
+                            Ok(_) => failures = 0,
+                            Err(error) if is_transient(&error) => {
+                                tokio::time::sleep(client.refresh_retry_delay(failures)).await;
+                                failures += 1;
+                            }
+                            // Retrying can't bring a revoked refresh token back
+                            Err(Error::AuthError { code, message, .. })
+                                if is_invalid_refresh_token(code.as_deref(), &message) =>
+                            {
+                                client.clear_session();
+                                break;
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    Some(_) => failures = 0,
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// Refresh the stored session `stale`, one caller at a time.
+    ///
+    /// Callers that waited for another refresh get the session it stored instead of spending the
+    /// rotated refresh token again.
+    async fn refresh_stored_session(&self, stale: &Session) -> Result<Session, Error> {
+        let _guard = self.refresh_lock.lock().await;
+
+        match self.session() {
+            Some(current) if current.refresh_token != stale.refresh_token => Ok(current),
+            Some(current) => self.refresh_session(&current.refresh_token).await,
+            None => Err(Error::NotAuthenticated),
+        }
+    }
+
+    /// Send a request authenticated with `bearer_token`.
+    ///
+    /// With [`retry_on_401`](AuthClientBuilder::retry_on_401) set and `bearer_token` being the
+    /// stored session's access token, a 401 refreshes the session and replays the request once
+    /// with the new token. If the refresh fails the 401 response is returned as is.
+    async fn send_with_bearer(
+        &self,
+        request: RequestBuilder,
+        bearer_token: &str,
+    ) -> Result<Response, Error> {
+        let replay = if self.retry_on_401 {
+            request.try_clone()
+        } else {
+            None
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| self.transport_error(error))?;
+
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let (Some(replay), Some(session)) = (replay, self.session()) else {
+            return Ok(response);
+        };
+
+        if session.access_token != bearer_token {
+            return Ok(response);
+        }
+
+        let Ok(refreshed) = self.refresh_stored_session(&session).await else {
+            return Ok(response);
+        };
+
+        let mut replay = replay.build()?;
+        replay.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", refreshed.access_token))?,
+        );
+
+        self.client
+            .execute(replay)
+            .await
+            .map_err(|error| self.transport_error(error))
+    }
+
+    /// The delay before retrying after `failures` failed refreshes: exponential backoff capped
+    /// at the maximum, with half of it randomized
+    fn refresh_retry_delay(&self, failures: u32) -> Duration {
+        let (initial, max_backoff) = self.refresh_backoff;
+        let delay = initial
+            .saturating_mul(2u32.saturating_pow(failures))
+            .min(max_backoff);
+
+        let half = delay / 2;
+        let jitter = self
+            .jitter
+            .lock()
+            .map(|mut rng| rng.u64(..=half.as_nanos() as u64))
+            .unwrap_or_default();
+
+        half + Duration::from_nanos(jitter)
+    }
+
+    /// The client clock's current time as a unix timestamp
+    fn unix_now(&self) -> u64 {
+        unix_time(self.clock.now())
+    }
+
+    /// Decode and verify the stored session's access token locally using the JWT secret,
+    /// without a round trip to the server.
+    ///
+    /// Returns [`Error::NotAuthenticated`] if there is no stored session.
+    /// # Example
+    /// ```
+    /// let claims = auth_client.current_claims().unwrap();
+    ///
+    /// println!("{} is {}", claims.sub, claims.role);
+    /// ```
+    pub fn current_claims(&self) -> Result<Claims, Error> {
+        let session = self.stored_session()?;
+
+        self.decode_jwt(&session.access_token)
+    }
+
+    /// Decode and verify a JWT signed with the client's JWT secret, returning its claims.
+    ///
+    /// Uses the default [`JwtValidation`], which requires GoTrue's `authenticated` audience.
+    /// Expired tokens and tokens with an invalid signature return [`Error::WrongToken`].
+    /// # Example
+    /// ```
+    /// let claims = auth_client.decode_jwt(&session.access_token).unwrap();
+    /// ```
+    pub fn decode_jwt(&self, token: &str) -> Result<Claims, Error> {
+        self.decode_jwt_with_validation(token, JwtValidation::default())
+    }
+
+    /// Decode and verify a JWT signed with the client's JWT secret, enforcing the audience and
+    /// issuer in `validation`.
+    ///
+    /// Returns [`Error::InvalidAudience`] or [`Error::InvalidIssuer`] on a mismatch, and
+    /// [`Error::WrongToken`] for expired tokens or an invalid signature.
+    /// # Example
+    /// ```
+    /// let validation = JwtValidation {
+    ///     expected_issuer: Some(format!("{}/auth/v1", project_url)),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let claims = auth_client
+    ///     .decode_jwt_with_validation(&session.access_token, validation)
+    ///     .unwrap();
+    /// ```
+    pub fn decode_jwt_with_validation(
+        &self,
+        token: &str,
+        validation: JwtValidation,
+    ) -> Result<Claims, Error> {
+        decode_verified(&self.jwt_secret, token, &validation)
+    }
+
+    /// Verify a JWT signed with the client's JWT secret and return its `sub` claim, the user id.
+    ///
+    /// Skips deserializing the rest of the claims, which makes it cheap enough to call on every
+    /// request in middleware. Returns [`Error::WrongToken`] for invalid tokens and for a `sub`
+    /// that isn't a UUID.
+    /// # Example
+    /// ```
+    /// let user_id = auth_client.user_id_from_token(&session.access_token).unwrap();
+    /// ```
+    pub fn user_id_from_token(&self, token: &str) -> Result<Uuid, Error> {
+        let claims =
+            decode_verified::<SubjectClaims>(&self.jwt_secret, token, &JwtValidation::default())?;
+
+        Uuid::parse_str(&claims.sub).map_err(|_| Error::WrongToken)
+    }
+
+    /// Verify a JWT signed with the client's JWT secret and build the user from its claims,
+    /// without asking the server, e.g. in a gateway where calling `/user` is too slow.
+    ///
+    /// The user only has what the token carries: id, audience, role, email, phone, metadata, and
+    /// whether it is anonymous. Other fields, like `identities` or `email_confirmed_at`, are
+    /// empty, and the values are as of when the token was issued. Use
+    /// [`get_user`](AuthClient::get_user) for the full record. Returns [`Error::WrongToken`] for
+    /// invalid tokens and for a `sub` that isn't a UUID.
+    /// # Example
+    /// ```
+    /// let user = auth_client.user_from_validated_token(bearer_token)?;
+    ///
+    /// if user.role() != Some("authenticated") {
+    ///     return Err(StatusCode::FORBIDDEN);
+    /// }
+    /// ```
+    pub fn user_from_validated_token(&self, token: &str) -> Result<User, Error> {
+        user_from_claims(self.decode_jwt(token)?)
+    }
+
+    /// The stored session, or why there is none
+    fn stored_session(&self) -> Result<Session, Error> {
+        match self.session() {
+            Some(session) => Ok(session),
+            None if !self.persist_session => Err(Error::SessionNotPersisted),
+            None => Err(Error::NotAuthenticated),
+        }
+    }
+
+    pub(crate) fn store_session(&self, session: &Session) {
+        if !self.persist_session {
+            return;
+        }
+
+        warn_on_long_refresh_margin(self.refresh_margin, &self.margin_warned_for, session);
+
+        self.session.send_replace(Some(session.clone()));
+    }
+
+    pub(crate) fn clear_session(&self) {
+        // Only notify receivers if there was a session to clear
+        self.session
+            .send_if_modified(|stored| stored.take().is_some());
+    }
+
+    /// Returns a client that applies `timeout` to each of its requests, overriding the timeout
+    /// of the underlying `reqwest::Client`. The returned client shares the connection pool and
+    /// stored session with the original.
+    /// # Example
+    /// ```
+    /// // Fail fast for health checks, while other calls keep the default
+    /// let health = auth_client
+    ///     .with_timeout(Duration::from_secs(2))
+    ///     .get_health()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> AuthClient {
+        AuthClient {
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a client that sends `api_key` as the `apikey` header instead of the stored key,
+    /// e.g. for gateways that route to a project by its key. The returned client shares the
+    /// connection pool and stored session with the original.
+    /// # Example
+    /// ```
+    /// let user = auth_client
+    ///     .with_api_key(&tenant_api_key)
+    ///     .get_user(&access_token)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub fn with_api_key(&self, api_key: &str) -> AuthClient {
+        AuthClient {
+            api_key: api_key.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// A transport error carrying this client's request timeout
+    fn transport_error(&self, source: reqwest::Error) -> Error {
+        Error::Transport {
+            source,
+            timeout: self.request_timeout,
+        }
+    }
+
+    /// Read the body of `response`, which the request timeout also applies to
+    async fn read_body(&self, response: Response) -> Result<String, Error> {
+        response
+            .text()
+            .await
+            .map_err(|error| self.transport_error(error))
+    }
+
+    /// Start a request with the per-request settings of this client applied
+    fn request(&self, method: Method, url: String) -> RequestBuilder {
+        let request = self.client.request(method, url);
+
+        match self.request_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Get the project URL from an AuthClient
+    pub fn project_url(&self) -> &str {
+        &self.project_url
+    }
+
+    /// Get the path GoTrue is served under, `/auth/v1` unless configured otherwise
+    pub fn auth_path(&self) -> &str {
+        &self.auth_path
+    }
+
+    /// Get the API Key from an AuthClient
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    /// Get the JWT Secret from an AuthClient
+    pub fn jwt_secret(&self) -> &str {
+        &self.jwt_secret
+    }
+}
+
+impl AuthClientBuilder {
+    /// Use your own `reqwest::Client`, e.g. to configure proxies, timeouts, or custom root
+    /// certificates. The client is used as-is, regardless of the enabled TLS feature.
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// How long before its expiry the stored session is refreshed by [`AuthClient::get_session`]
+    /// and [`AuthClient::start_auto_refresh`]. Defaults to 60 seconds.
+    ///
+    /// A margin that isn't smaller than a session's lifetime would refresh it continuously,
+    /// so for such sessions half of the lifetime is used instead, and a warning is logged
+    /// through the [`log`](https://docs.rs/log) facade the first time one is stored.
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// How long [`AuthClient::get_settings`] reuses the last fetched settings. Defaults to
+    /// 5 minutes, use [`Duration::ZERO`] to disable the cache.
+    pub fn settings_ttl(mut self, ttl: Duration) -> Self {
+        self.settings_ttl = ttl;
+        self
+    }
+
+    /// The path GoTrue is served under, relative to the project URL. Defaults to `/auth/v1`,
+    /// change it if your self-hosted GoTrue is behind a different prefix.
+    pub fn auth_path(mut self, path: impl Into<String>) -> Self {
+        self.auth_path = path.into();
+        self
+    }
+
+    /// The clock used to decide when sessions expire, e.g. a [`MockClock`](crate::clock::MockClock)
+    /// in tests. Defaults to the system clock.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Whether the client stores the sessions returned by logins, sign ups, and refreshes.
+    /// Defaults to true.
+    ///
+    /// Turn it off for a client shared by many users, e.g. on a server handling requests with
+    /// their own tokens, so one user's session never leaks into another request. The methods
+    /// that work on the stored session then return [`Error::SessionNotPersisted`].
+    pub fn persist_session(mut self, persist_session: bool) -> Self {
+        self.persist_session = persist_session;
+        self
+    }
+
+    /// How long [`AuthClient::start_auto_refresh`] waits before retrying a failed refresh. The
+    /// delay starts at `initial` and doubles with each failure up to `max_backoff`. Defaults to
+    /// 10 seconds and 5 minutes.
+    ///
+    /// Each delay is randomized between half and all of it, so many clients whose refreshes
+    /// failed at the same time, e.g. during a short outage, don't retry in lockstep.
+    pub fn refresh_backoff(mut self, initial: Duration, max_backoff: Duration) -> Self {
+        self.refresh_backoff = (initial, max_backoff);
+        self
+    }
+
+    /// Seed the randomness of the delays between failed refreshes, so they are the same on
+    /// every run, e.g. in tests. By default each client is seeded randomly.
+    pub fn jitter_seed(mut self, seed: u64) -> Self {
+        self.jitter_seed = Some(seed);
+        self
+    }
+
+    /// Whether a 401 to a request made with the stored session's access token refreshes the
+    /// session and replays the request with the new token. Defaults to false.
+    ///
+    /// Only one retry is made: if the replayed request gets a 401 too, or the refresh fails, the
+    /// original error is returned. Requests made with any other token, e.g. a service role key,
+    /// are never retried.
+    pub fn retry_on_401(mut self, retry_on_401: bool) -> Self {
+        self.retry_on_401 = retry_on_401;
+        self
+    }
+
+    /// Build the [`AuthClient`]
+    ///
+    /// Trailing slashes are trimmed from the project URL. Returns [`Error::ParseUrlError`] if it
+    /// isn't a valid URL.
+    pub fn build(self) -> Result<AuthClient, Error> {
+        let project_url = self.project_url.trim_end_matches('/').to_owned();
+        Url::parse(&project_url).map_err(|_| Error::ParseUrlError)?;
+
+        Ok(AuthClient {
+            client: self.http_client.unwrap_or_default(),
+            project_url,
+            api_key: self.api_key,
+            jwt_secret: self.jwt_secret,
+            session: Arc::default(),
+            request_timeout: None,
+            refresh_margin: self.refresh_margin,
+            settings_cache: Arc::default(),
+            settings_ttl: self.settings_ttl,
+            auth_path: self.auth_path.trim_end_matches('/').to_owned(),
+            clock: self.clock,
+            refresh_lock: Arc::default(),
+            persist_session: self.persist_session,
+            refresh_backoff: self.refresh_backoff,
+            margin_warned_for: Arc::default(),
+            jitter: Arc::new(Mutex::new(match self.jitter_seed {
+                Some(seed) => fastrand::Rng::with_seed(seed),
+                None => fastrand::Rng::new(),
+            })),
+            retry_on_401: self.retry_on_401,
+        })
+    }
+}
+
+/// Whether a failed request may succeed when retried: the server couldn't be reached, errored,
+/// or rate limited the client
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::Transport { .. } | Error::RateLimited { .. } => true,
+        Error::AuthError { status, .. } | Error::UnexpectedResponse { status, .. } => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        _ => false,
     }
 }
@@ -0,0 +1,76 @@
+/*!
+Time source used to decide when sessions expire and need to be refreshed.
+
+The client uses [`SystemClock`] unless another [`Clock`] is set with
+[`AuthClientBuilder::clock`](crate::models::AuthClientBuilder::clock). [`MockClock`] lets tests
+move time forward without sleeping:
+
+```rust
+let clock = MockClock::new(SystemTime::now());
+
+let auth_client = AuthClient::builder(project_url, api_key, jwt_secret)
+    .clock(clock.clone())
+    .build()
+    .unwrap();
+
+clock.advance(Duration::from_secs(3600));
+```
+*/
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// A source of the current time
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system's wall clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl MockClock {
+    pub fn new(now: SystemTime) -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Jump to `now`
+    pub fn set(&self, now: SystemTime) {
+        if let Ok(mut current) = self.now.lock() {
+            *current = now;
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut current) = self.now.lock() {
+            *current += duration;
+        }
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        self.now
+            .lock()
+            .map(|now| *now)
+            .unwrap_or_else(|_| SystemTime::now())
+    }
+}
@@ -4,15 +4,22 @@ Provides error types and handling for Supabase authentication operations.
 This module defines the primary [`Error`] enum and supporting types for handling various
 error conditions that may occur during authentication operations.
 
-The most common error is [`Error::Supabase`] which wraps [`SupabaseHTTPError`]. This is a transparent error which gives you the `status` as a [`StatusCode`] and `message` from the Supabase response.
+The most common error is [`Error::AuthError`], returned when the server rejects a request. It gives you the `status` as a [`StatusCode`], GoTrue's error `code` if present, and the `message` from the Supabase response.
+When the response couldn't be understood at all, e.g. an HTML page from a proxy, you get
+[`Error::UnexpectedResponse`] instead, with the raw body and the method and path of the request.
+
+Failures to reach the server are returned as [`Error::Transport`] and can usually be retried, while
+[`Error::Deserialization`] means a body didn't have the expected shape. Both keep the underlying
+error as their [`source`](std::error::Error::source).
 */
 
 use std::{
     env,
     fmt::{self, Display},
+    time::Duration,
 };
 
-use reqwest::StatusCode;
+use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -22,33 +29,118 @@ pub enum Error {
     AlreadySignedUp,
     #[error("Invalid Credentials")]
     WrongCredentials,
+    #[error("Email Not Confirmed")]
+    EmailNotConfirmed,
+    /// The token was accepted, but the email change still has to be confirmed from the other address
+    #[error("Email Change Pending")]
+    EmailChangePending,
     #[error("User Not Found")]
     UserNotFound,
     #[error("Supabase Client not Authenticated")]
     NotAuthenticated,
+    /// The client was built with `persist_session(false)`, so there is no stored session to use
+    #[error("Session Persistence is Disabled")]
+    SessionNotPersisted,
+    /// The email wasn't confirmed before the timeout passed to
+    /// [`AuthClient::wait_for_confirmation`](crate::models::AuthClient::wait_for_confirmation)
+    #[error("Timed Out Waiting for Email Confirmation")]
+    ConfirmationTimeout,
     #[error("Missing Refresh Token")]
     MissingRefreshToken,
+    /// The refresh token is unknown, was revoked, or was already used
+    #[error("Invalid Refresh Token")]
+    InvalidRefreshToken,
     #[error("JWT Is Invalid")]
     WrongToken,
+    /// The JWT's `aud` claim is missing or doesn't match the expected audience
+    #[error("JWT Audience Mismatch")]
+    InvalidAudience,
+    /// The JWT's `iss` claim is missing or doesn't match the expected issuer
+    #[error("JWT Issuer Mismatch")]
+    InvalidIssuer,
     #[error("Internal Error")]
     InternalError,
-    #[error("Network Error")]
-    NetworkError(#[from] reqwest::Error),
+    /// The request couldn't be sent or the response couldn't be read, e.g. the server is
+    /// unreachable or the request timed out
+    #[error("{}", describe_transport(source, *timeout))]
+    Transport {
+        source: reqwest::Error,
+        /// The request timeout the client was configured with, if any
+        timeout: Option<Duration>,
+    },
+    /// A request or response body couldn't be (de)serialized
     #[error("Failed to Parse")]
-    ParseError(#[from] serde_json::Error),
+    Deserialization(#[from] serde_json::Error),
     #[error("Header Value is Invalid")]
     InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
     #[error("Environment Variable Unreadable")]
     InvalidEnvironmentVariable(#[from] env::VarError),
     #[error("Failed to parse URL")]
     ParseUrlError,
+    #[error("OAuth State Mismatch")]
+    StateMismatch,
+    #[error("Unknown Provider: {0}")]
+    UnknownProvider(String),
     #[error("{0}")]
     Supabase(SupabaseHTTPError),
+    /// Too many requests, e.g. password reset emails or OTPs. `retry_after` is taken from the
+    /// `Retry-After` header if the server sent one.
+    #[error("Rate Limited")]
+    RateLimited { retry_after: Option<Duration> },
+    /// The server rejected the request
     #[error("Error: {status}: {message}")]
-    AuthError { status: StatusCode, message: String },
+    AuthError {
+        status: StatusCode,
+        /// GoTrue's `error_code`, e.g. `invalid_credentials`, when the server sent one
+        code: Option<String>,
+        message: String,
+    },
+    /// The server responded with a body in neither the expected nor GoTrue's error shape
+    #[error("Unexpected Response: {method} {path}: {status}: {body}")]
+    UnexpectedResponse {
+        status: StatusCode,
+        method: Method,
+        /// The path of the request, without the query string
+        path: String,
+        body: String,
+    },
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(source: reqwest::Error) -> Self {
+        Error::Transport {
+            source,
+            timeout: None,
+        }
+    }
+}
+
+/// Say what went wrong in the message itself, so logs are actionable without the source chain
+fn describe_transport(error: &reqwest::Error, timeout: Option<Duration>) -> String {
+    let url = error
+        .url()
+        .map(|url| format!(" ({}{})", url.host_str().unwrap_or_default(), url.path()))
+        .unwrap_or_default();
+
+    if error.is_timeout() {
+        match timeout {
+            Some(timeout) => format!("request timed out after {:?}{}", timeout, url),
+            None => format!("request timed out{}", url),
+        }
+    } else if error.is_connect() {
+        format!("connection failed{}", url)
+    } else {
+        format!("transport error{}", url)
+    }
 }
 
+/// An error body returned by GoTrue.
+///
+/// Depending on the version and endpoint the message is in `msg`, `message`,
+/// `error_description`, or `error`, and all of them are accepted. OAuth style bodies with both
+/// `error` and `error_description` use `error` as the error code.
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "RawSupabaseHTTPError")]
 pub struct SupabaseHTTPError {
     pub code: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -63,6 +155,66 @@ pub struct SupabaseHTTPError {
     pub error_id: Option<String>,
 }
 
+/// Every shape of GoTrue error body, normalized into [`SupabaseHTTPError`]
+#[derive(Deserialize)]
+struct RawSupabaseHTTPError {
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+    #[serde(default)]
+    error_code: Option<String>,
+    #[serde(default)]
+    msg: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    error_description: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    internal_error: Option<serde_json::Value>,
+    #[serde(default)]
+    internal_message: Option<serde_json::Value>,
+    #[serde(default)]
+    error_id: Option<String>,
+}
+
+impl TryFrom<RawSupabaseHTTPError> for SupabaseHTTPError {
+    type Error = &'static str;
+
+    fn try_from(raw: RawSupabaseHTTPError) -> Result<Self, Self::Error> {
+        // `error` is a code when it comes with a description, and the message otherwise
+        let (error_as_code, error_as_message) = match raw.error_description {
+            Some(_) => (raw.error, None),
+            None => (None, raw.error),
+        };
+
+        // Newer endpoints send the error code as a string in `code`
+        let (code, code_as_error_code) = match raw.code {
+            Some(serde_json::Value::Number(code)) => {
+                (code.as_i64().unwrap_or_default() as i32, None)
+            }
+            Some(serde_json::Value::String(code)) => (0, Some(code)),
+            _ => (0, None),
+        };
+
+        let message = raw
+            .msg
+            .or(raw.message)
+            .or(raw.error_description)
+            .or(error_as_message)
+            .ok_or("missing error message")?;
+
+        Ok(SupabaseHTTPError {
+            code,
+            error_code: raw.error_code.or(code_as_error_code).or(error_as_code),
+            message,
+            internal_error: raw.internal_error,
+            internal_message: raw.internal_message,
+            error_id: raw.error_id,
+        })
+    }
+}
+
 impl Display for SupabaseHTTPError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Status Code {}", self.code)?;
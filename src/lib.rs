@@ -1,6 +1,9 @@
 #![cfg(not(doctest))]
 #![forbid(unsafe_code)]
 #![deny(missing_debug_implementations)]
+#![allow(clippy::result_large_err)]
+// Without a client only the models are left, the request payloads and helpers go unused
+#![cfg_attr(not(any(feature = "async", feature = "blocking")), allow(dead_code))]
 
 /*!
 # Supabase Auth
@@ -42,20 +45,86 @@ let auth_client = AuthClient::new(project_url, api_key, jwt_secret).unwrap();
 // Or you can use environment variables
 // Requires `SUPABASE_URL`, `SUPABASE_API_KEY`, and `SUPABASE_JWT_SECRET` environment variables
 let auth_client = AuthClient::new_from_env().unwrap();
+
+// Or use the builder to customize the client, e.g. with your own reqwest::Client
+let auth_client = AuthClient::builder(project_url, api_key, jwt_secret)
+    .http_client(reqwest::Client::new())
+    .build()
+    .unwrap();
+```
+
+### TLS
+
+A TLS backend is required for `https` project URLs. `rustls` is used by default. To use the system's native TLS stack (and certificate store) instead:
+
+```toml
+[dependencies]
+supabase-auth = { version = "0.10", default-features = false, features = ["native-tls", "async"] }
+```
+
+With `default-features = false` and neither `rustls` nor `native-tls` enabled, requests to
+`https` URLs fail at runtime. Enabling both is allowed, in which case `native-tls` is used.
+
+### Blocking client
+
+Enable the `blocking` feature for a synchronous client built on `reqwest::blocking`, with the
+same methods minus `async`. Turning off the default `async` feature drops the async client and
+the crate's tokio dependency:
+
+```toml
+[dependencies]
+supabase-auth = { version = "0.10", default-features = false, features = ["rustls", "blocking"] }
+```
+
+Like `reqwest::blocking`, it panics when used from within an async runtime.
+
+```rust
+let auth_client = supabase_auth::blocking::AuthClient::new(project_url, api_key, jwt_secret)?;
+
+let session = auth_client.login_with_email(&demo_email, &demo_password)?;
+```
+
+### Cancellation
+
+Every async method is cancel safe: the client only stores, replaces, or clears its session and
+cached settings after the full response was received and parsed. Dropping a call midway, e.g.
+with `tokio::time::timeout` or `tokio::select!`, leaves the client as it was before the call.
+The request itself may still have reached the server, so a dropped `logout` can have revoked the
+tokens the client still holds.
+
+```rust
+let login = auth_client.login_with_email(demo_email, demo_password);
+
+if tokio::time::timeout(Duration::from_secs(5), login).await.is_err() {
+    // Timed out, nothing was stored
+    assert!(!auth_client.is_authenticated());
+}
 ```
 
 ### Sign Up
 
 ```rust
 // Sign up methods return the session which you can use for creating cookies
-let session = auth_client
-    .sign_up_with_email_and_password(demo_email, demo_password)
+let result = auth_client
+    .sign_up_with_email_and_password(demo_email, demo_password, None)
     .await
     .unwrap();
 
 // You can also sign up using a phone number and password
 let session = auth_client
-    .sign_up_with_phone_and_password(demo_phone, demo_password)
+    .sign_up_with_phone_and_password(demo_phone, demo_password, None)
+    .await
+    .unwrap();
+
+// Options implement `Default`, so only set the fields you need. `data` is stored as the
+// user's metadata.
+let options = SignUpWithPasswordOptions {
+    data: Some(json!({ "display_name": "Demo" })),
+    ..Default::default()
+};
+
+let result = auth_client
+    .sign_up_with_email_and_password(demo_email, demo_password, Some(options))
     .await
     .unwrap();
 ```
@@ -94,8 +163,9 @@ query_params.insert("third_key".to_string(), "third_value".to_string());
 let options = SignInWithOAuthOptions {
     query_params: Some(query_params),
     redirect_to: Some("your-redirect-url".to_string()),
-    scopes: Some("repo gist notifications".to_string()),
+    scopes: Some(vec!["repo".to_string(), "gist".to_string()]),
     skip_browser_redirect: Some(true),
+    ..Default::default()
 };
 
 let response = auth_client
@@ -138,6 +208,18 @@ let url = auth_client.sso(params).await.unwrap();
 * ✓ SSO
 */
 
+#[cfg(feature = "async")]
+pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "async")]
 pub mod client;
+pub mod clock;
 pub mod error;
 pub mod models;
+mod shared;
+
+pub use error::Error;
+#[cfg(feature = "async")]
+pub use models::AuthClient;
+pub use models::{OtpType, ResendParams};
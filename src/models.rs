@@ -1,13 +1,32 @@
 #![cfg(not(doctest))]
 
 use core::fmt;
-use reqwest::{Client, Url};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::{collections::HashMap, fmt::Display};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use uuid::Uuid;
 
+use crate::error::Error;
+
+#[cfg(feature = "async")]
+use crate::clock::Clock;
+#[cfg(feature = "async")]
+use reqwest::Client;
+#[cfg(feature = "async")]
+use std::{
+    sync::{atomic::AtomicI64, Arc, Mutex, RwLock},
+    time::Instant,
+};
+
 /// Supabase Auth Client
+#[cfg(feature = "async")]
 #[derive(Clone)]
 pub struct AuthClient {
     pub(crate) client: Client,
@@ -18,11 +37,83 @@ pub struct AuthClient {
     pub(crate) api_key: String,
     /// Used to decode your JWTs. You can also use this to mint your own JWTs.
     pub(crate) jwt_secret: String,
+    /// Session of the signed in user, stored by the login, sign up, and refresh methods.
+    /// Receivers from [`AuthClient::session_watch`] are notified when it changes.
+    pub(crate) session: Arc<tokio::sync::watch::Sender<Option<Session>>>,
+    /// Timeout applied to each request, set with [`AuthClient::with_timeout`]
+    pub(crate) request_timeout: Option<Duration>,
+    /// How long before expiry the stored session is refreshed
+    pub(crate) refresh_margin: Duration,
+    /// Settings returned by the last [`AuthClient::get_settings`] call and when they were fetched
+    pub(crate) settings_cache: Arc<RwLock<Option<(Instant, AuthServerSettings)>>>,
+    /// How long cached settings are reused
+    pub(crate) settings_ttl: Duration,
+    /// Path GoTrue is served under, relative to the project url
+    pub(crate) auth_path: String,
+    /// Time source for session expiry, [`SystemClock`](crate::clock::SystemClock) by default
+    pub(crate) clock: Arc<dyn Clock>,
+    /// Held while the stored session is refreshed, so concurrent callers share a single refresh
+    pub(crate) refresh_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Whether sessions returned by the server are stored, see [`AuthClientBuilder::persist_session`]
+    pub(crate) persist_session: bool,
+    /// First and longest delay between failed refreshes of [`AuthClient::start_auto_refresh`]
+    pub(crate) refresh_backoff: (Duration, Duration),
+    /// The session lifetime the refresh margin was last found too long for, so each lifetime is
+    /// only warned about once
+    pub(crate) margin_warned_for: Arc<AtomicI64>,
+    /// Randomizes the delays between failed refreshes, see [`AuthClientBuilder::jitter_seed`]
+    pub(crate) jitter: Arc<Mutex<fastrand::Rng>>,
+    /// Whether a 401 to the stored session's token refreshes it and replays the request once
+    pub(crate) retry_on_401: bool,
+}
+
+/// Builder for an [`AuthClient`], created with [`AuthClient::builder`]
+#[cfg(feature = "async")]
+pub struct AuthClientBuilder {
+    pub(crate) project_url: String,
+    pub(crate) api_key: String,
+    pub(crate) jwt_secret: String,
+    pub(crate) http_client: Option<Client>,
+    pub(crate) refresh_margin: Duration,
+    pub(crate) settings_ttl: Duration,
+    pub(crate) auth_path: String,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) persist_session: bool,
+    pub(crate) refresh_backoff: (Duration, Duration),
+    pub(crate) jitter_seed: Option<u64>,
+    pub(crate) retry_on_401: bool,
+}
+
+/// Builds the OAuth authorize URL without an [`AuthClient`], e.g. when rendering a login page
+///
+/// # Example
+/// ```
+/// let response = OAuthUrlBuilder::new(project_url, Provider::Github)
+///     .redirect_to("http://localhost:3000/auth/callback")
+///     .scopes("repo gist")
+///     .pkce_challenge(code_challenge)
+///     .build()
+///     .unwrap();
+///
+/// println!("Log in at {}", response.url);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthUrlBuilder {
+    pub(crate) project_url: String,
+    pub(crate) auth_path: String,
+    pub(crate) provider: Provider,
+    pub(crate) redirect_to: Option<String>,
+    pub(crate) scopes: Option<String>,
+    pub(crate) query_params: Vec<(String, String)>,
+    pub(crate) state: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Session {
     /// The oauth provider token. If present, this can be used to make external API requests to the oauth provider used.
+    ///
+    /// GoTrue doesn't return the scopes the provider granted, the token carries the `scopes`
+    /// requested in [`LoginWithOAuthOptions`] unless the user declined some of them.
     pub provider_token: Option<String>,
     /// The oauth provider refresh token. If present, this can be used to refresh the provider_token via the oauth provider's API.
     ///
@@ -38,6 +129,140 @@ pub struct Session {
     /// A one-time used refresh token that never expires.
     pub refresh_token: String,
     pub user: User,
+    /// Whether this session created the user, where GoTrue reports it. `None` if it didn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_new_user: Option<bool>,
+    /// Fields returned by the server that this crate doesn't model yet
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Session {
+    /// Returns true if the access token has expired, by the system clock.
+    ///
+    /// This ignores a [`Clock`](crate::clock::Clock) given to the client, use
+    /// [`AuthClient::session_expired`] or [`expires_within_at`](Session::expires_within_at)
+    /// to check against it.
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::ZERO)
+    }
+
+    /// Returns true if the access token expires within `margin` from now, by the system clock.
+    ///
+    /// Like [`is_expired`](Session::is_expired), this ignores the client's clock, see
+    /// [`AuthClient::session_expires_within`].
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_within_at(margin, SystemTime::now())
+    }
+
+    /// Returns true if the access token expires within `margin` from `now`
+    pub fn expires_within_at(&self, margin: Duration, now: SystemTime) -> bool {
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        now.as_secs() + margin.as_secs() >= self.expires_at
+    }
+
+    /// The assurance level of the session, read from the `aal` claim of the access token after
+    /// verifying its signature with `key`, made from the project's JWT secret. Returns `None` if
+    /// the token has no `aal` claim, and [`Error::WrongToken`] if the signature is invalid.
+    ///
+    /// Expiry isn't checked, see [`is_expired`](Session::is_expired).
+    /// # Example
+    /// ```
+    /// let key = DecodingKey::from_secret(jwt_secret.as_bytes());
+    ///
+    /// let aal = session.authenticator_assurance_level(&key).unwrap();
+    /// ```
+    pub fn authenticator_assurance_level(
+        &self,
+        key: &DecodingKey,
+    ) -> Result<Option<AuthenticatorAssuranceLevel>, Error> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_aud = false;
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+
+        let token_data = decode::<AalClaims>(&self.access_token, key, &validation)
+            .map_err(|_| Error::WrongToken)?;
+
+        Ok(token_data.claims.aal)
+    }
+}
+
+/// How strongly a session was authenticated
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthenticatorAssuranceLevel {
+    /// Signed in with a single factor, e.g. a password or magic link
+    Aal1,
+    /// Also verified a second factor, e.g. a TOTP code
+    Aal2,
+}
+
+#[derive(Deserialize)]
+struct AalClaims {
+    #[serde(default)]
+    aal: Option<AuthenticatorAssuranceLevel>,
+}
+
+/// Claims carried by a Supabase access token
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Claims {
+    /// The user id
+    pub sub: String,
+    #[serde(default)]
+    pub aud: String,
+    /// Expiration time as a unix timestamp
+    pub exp: u64,
+    /// Issued at time as a unix timestamp
+    #[serde(default)]
+    pub iat: u64,
+    #[serde(default)]
+    pub iss: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    pub role: String,
+    /// Authenticator assurance level, `aal1` or `aal2`
+    #[serde(default)]
+    pub aal: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub is_anonymous: bool,
+    #[serde(default)]
+    pub app_metadata: AppMetadata,
+    #[serde(default)]
+    pub user_metadata: UserMetadata,
+}
+
+/// The only claim [`AuthClient::user_id_from_token`] needs
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubjectClaims {
+    pub(crate) sub: String,
+}
+
+/// Checks applied by [`AuthClient::decode_jwt_with_validation`] on top of the signature and expiry
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtValidation {
+    /// The required `aud` claim, or `None` to accept any audience
+    pub expected_audience: Option<String>,
+    /// The required `iss` claim, e.g. `https://<project>.supabase.co/auth/v1`, or `None` to accept any issuer
+    pub expected_issuer: Option<String>,
+    /// Allowed clock skew when checking the expiry
+    pub leeway: Duration,
+}
+
+impl Default for JwtValidation {
+    /// GoTrue's `authenticated` audience, any issuer, and a leeway of 60 seconds
+    fn default() -> Self {
+        JwtValidation {
+            expected_audience: Some("authenticated".to_string()),
+            expected_issuer: None,
+            leeway: Duration::from_secs(60),
+        }
+    }
 }
 
 /// User respresents a registered user
@@ -62,12 +287,88 @@ pub struct User {
     pub recovery_sent_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_sign_in_at: Option<String>,
+    /// Set while the user is banned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banned_until: Option<String>,
     pub app_metadata: AppMetadata,
     pub user_metadata: UserMetadata,
     pub identities: Vec<Identity>,
     pub created_at: String,
     pub updated_at: String,
     pub is_anonymous: bool,
+    /// Multi-factor authentication factors the user enrolled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub factors: Vec<Factor>,
+    /// Fields returned by the server that this crate doesn't model yet
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A multi-factor authentication factor of a user
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Factor {
+    pub id: Uuid,
+    #[serde(default)]
+    pub friendly_name: Option<String>,
+    /// `totp` or `phone`
+    pub factor_type: String,
+    /// `verified` or `unverified`
+    pub status: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl Factor {
+    /// Returns true if the user completed enrolling this factor
+    pub fn is_verified(&self) -> bool {
+        self.status == "verified"
+    }
+}
+
+impl User {
+    /// Deserialize the user's `user_metadata` into your own type
+    ///
+    /// # Example
+    /// ```
+    /// #[derive(Deserialize)]
+    /// struct Profile {
+    ///     display_name: String,
+    /// }
+    ///
+    /// let profile: Profile = user.metadata()?;
+    /// ```
+    pub fn metadata<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_value(serde_json::to_value(
+            &self.user_metadata,
+        )?)?)
+    }
+
+    /// Returns true if the user signed in anonymously
+    pub fn is_anonymous(&self) -> bool {
+        self.is_anonymous
+    }
+
+    /// The user's role, e.g. `authenticated`. Returns `None` if the server didn't set one.
+    pub fn role(&self) -> Option<&str> {
+        Some(self.role.as_str()).filter(|role| !role.is_empty())
+    }
+
+    /// Returns true if the user has confirmed their email address
+    pub fn has_confirmed_email(&self) -> bool {
+        self.email_confirmed_at.is_some()
+    }
+
+    /// The providers the user has linked, e.g. `["email", "github"]`
+    pub fn linked_providers(&self) -> Vec<String> {
+        self.app_metadata.providers.clone().unwrap_or_default()
+    }
+
+    /// The provider the user first signed up with
+    pub fn primary_provider(&self) -> Option<String> {
+        self.app_metadata.provider.clone()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -76,6 +377,8 @@ pub struct AppMetadata {
     pub provider: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub providers: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub custom: HashMap<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -99,11 +402,35 @@ pub struct UserMetadata {
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum EmailSignUpResult {
     SessionResult(Session),
     ConfirmationResult(EmailSignUpConfirmation),
 }
 
+impl EmailSignUpResult {
+    /// The session, if the user was signed in right away
+    pub fn session(&self) -> Option<&Session> {
+        match self {
+            EmailSignUpResult::SessionResult(session) => Some(session),
+            EmailSignUpResult::ConfirmationResult(_) => None,
+        }
+    }
+
+    /// The pending confirmation, if the user has to confirm their email first
+    pub fn confirmation(&self) -> Option<&EmailSignUpConfirmation> {
+        match self {
+            EmailSignUpResult::SessionResult(_) => None,
+            EmailSignUpResult::ConfirmationResult(confirmation) => Some(confirmation),
+        }
+    }
+
+    /// Returns true if the user has to confirm their email before they can sign in
+    pub fn is_confirmation_required(&self) -> bool {
+        matches!(self, EmailSignUpResult::ConfirmationResult(_))
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Default)]
 pub struct EmailSignUpConfirmation {
     pub id: Uuid,
@@ -125,18 +452,31 @@ pub struct IdTokenCredentials {
     #[serde(rename = "id_token")]
     pub token: String,
     /// If the ID token contains an at_hash claim, then the hash of this value is compared to the value in the ID token.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token: Option<String>,
     /// If the ID token contains a nonce claim, then the hash of this value is compared to the value in the ID token.
+    ///
+    /// Required for Sign in with Apple, which always puts a nonce in the ID token.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub nonce: Option<String>,
     /// Optional Object which may contain a captcha token
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub gotrue_meta_security: Option<GotrueMetaSecurity>,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Default)]
 pub struct LoginWithOAuthOptions {
+    /// Additional query parameters added to the authorize URL
     pub query_params: Option<HashMap<String, String>>,
+    /// A URL to send the user to after they are confirmed.
     pub redirect_to: Option<String>,
-    pub scopes: Option<String>,
+    /// Scopes to request from the provider, e.g. `["repo", "read:org"]`
+    pub scopes: Option<Vec<String>>,
+    /// Scopes as a single space-separated string, e.g. `"repo read:org"`. Sent before
+    /// `scopes` if both are set.
+    pub scopes_str: Option<String>,
+    /// This client never redirects on its own; the authorize URL is always returned in the
+    /// [`OAuthResponse`] for you to redirect to. Kept for parity with the JS client.
     pub skip_browser_redirect: Option<bool>,
 }
 
@@ -144,35 +484,69 @@ pub struct LoginWithOAuthOptions {
 pub struct OAuthResponse {
     pub url: Url,
     pub provider: Provider,
+    /// Random value sent as the `state` query parameter of the authorize URL.
+    ///
+    /// Keep it (e.g. in a cookie) and check it against the callback with
+    /// [`AuthClient::verify_oauth_state`] to protect the flow against CSRF.
+    pub state: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GotrueMetaSecurity {
     /// Verification token received when the user completes the captcha on the site.
-    captcha_token: Option<String>,
+    pub captcha_token: Option<String>,
+}
+
+impl From<LoginWithPasswordOptions> for GotrueMetaSecurity {
+    fn from(options: LoginWithPasswordOptions) -> Self {
+        GotrueMetaSecurity {
+            captcha_token: options.captcha_token,
+        }
+    }
 }
 
+/// An account with an auth provider linked to a user, e.g. their GitHub or Google account
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Identity {
+    #[serde(default)]
     pub identity_id: String,
+    /// The user's id with the provider
     pub id: String,
     pub user_id: String,
+    #[serde(default)]
     pub identity_data: IdentityData,
     pub provider: String,
-    pub last_sign_in_at: String,
+    #[serde(default)]
+    pub last_sign_in_at: Option<String>,
     pub created_at: String,
+    #[serde(default)]
     pub updated_at: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
 }
 
+/// Profile data from the provider. What's available depends on the provider.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IdentityData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
+    #[serde(default)]
     pub email_verified: bool,
+    #[serde(default)]
     pub phone_verified: bool,
+    #[serde(default)]
     pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    /// Other provider specific fields
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -185,12 +559,22 @@ pub enum LoginOptions {
 pub(crate) struct LoginWithEmailAndPasswordPayload<'a> {
     pub(crate) email: &'a str,
     pub(crate) password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gotrue_meta_security: Option<GotrueMetaSecurity>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct LoginWithPhoneAndPasswordPayload<'a> {
     pub(crate) phone: &'a str,
     pub(crate) password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gotrue_meta_security: Option<GotrueMetaSecurity>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginWithPasswordOptions {
+    /// Verification token received when the user completes the captcha on the site.
+    pub captcha_token: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -220,7 +604,9 @@ pub(crate) struct LoginAnonymouslyPayload {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SignUpWithPasswordOptions {
-    /// The redirect url embedded in the email link
+    /// The redirect url embedded in the email link. It has to match the project's Redirect URLs
+    /// allowlist, GoTrue uses the site URL instead of one that doesn't. A redirect related
+    /// [`Error::AuthError`] names the rejected URL in its message.
     #[serde(skip)]
     pub email_redirect_to: Option<String>,
     /// A custom data object to store the user's metadata. This maps to the `auth.users.raw_user_meta_data` column.
@@ -233,7 +619,9 @@ pub struct SignUpWithPasswordOptions {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResetPasswordOptions {
-    /// The redirect url embedded in the email link
+    /// The redirect url embedded in the email link. It has to match the project's Redirect URLs
+    /// allowlist, GoTrue uses the site URL instead of one that doesn't. A redirect related
+    /// [`Error::AuthError`] names the rejected URL in its message.
     #[serde(skip)]
     pub email_redirect_to: Option<String>,
 
@@ -243,9 +631,14 @@ pub struct ResetPasswordOptions {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LoginAnonymouslyOptions {
+    /// A custom data object to store the user's metadata. It is sent as the top-level `data`
+    /// field and maps to the `auth.users.raw_user_meta_data` column.
+    ///
     /// The `data` should be a JSON object that includes user-specific info, such as their first and last name.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Value>,
     /// Verification token received when the user completes the captcha on the site.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub captcha_token: Option<String>,
 }
 
@@ -254,22 +647,108 @@ pub(crate) struct RequestMagicLinkPayload<'a> {
     pub(crate) email: &'a str,
 }
 
+/// Fields a user can change on their own account with [`AuthClient::update_user`]
+///
+/// Privileged fields like `app_metadata`, `role`, or `ban_duration` are deliberately missing,
+/// change them with [`AuthClient::admin_update_user_by_id`] and [`AdminUserAttributes`] instead.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UpdatedUser {
     pub email: Option<String>,
     pub password: Option<String>,
+    /// Merged into the user's `user_metadata`: GoTrue keeps keys that aren't sent and removes
+    /// keys sent as `null`. See [`UpdatedUser::merge_metadata`] and
+    /// [`UpdatedUser::replace_metadata`].
     pub data: Option<serde_json::Value>,
+    /// The code sent by [`AuthClient::reauthenticate`], required to change the password when
+    /// "Secure password change" is enabled and the user didn't sign in recently
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl UpdatedUser {
+    /// An update that only sets the user's `user_metadata` to `data`, serialized from your own
+    /// type. The fields of `data` are merged into the existing metadata.
+    ///
+    /// # Example
+    /// ```
+    /// let updated_user = UpdatedUser::with_metadata(&Profile {
+    ///     display_name: "Demo".to_string(),
+    /// })?;
+    /// ```
+    pub fn with_metadata<T: Serialize>(data: &T) -> Result<UpdatedUser, Error> {
+        Ok(UpdatedUser {
+            data: Some(serde_json::to_value(data)?),
+            ..Default::default()
+        })
+    }
+
+    /// An update that merges `delta` into the user's `user_metadata`. Keys that aren't in
+    /// `delta` are kept, set a key to `null` to remove it.
+    ///
+    /// # Example
+    /// ```
+    /// let updated_user = UpdatedUser::merge_metadata(
+    ///     json!({ "theme": "dark", "beta_tester": null }).as_object().unwrap().clone(),
+    /// );
+    /// ```
+    pub fn merge_metadata(delta: Map<String, Value>) -> UpdatedUser {
+        UpdatedUser {
+            data: Some(Value::Object(delta)),
+            ..Default::default()
+        }
+    }
+
+    /// An update that replaces the user's `user_metadata`, `current`, with `data`.
+    ///
+    /// GoTrue only merges metadata, so every key of `current` that's missing from `data` is sent
+    /// as `null` to remove it.
+    ///
+    /// # Example
+    /// ```
+    /// let updated_user = UpdatedUser::replace_metadata(
+    ///     &user.user_metadata,
+    ///     json!({ "theme": "dark" }).as_object().unwrap().clone(),
+    /// );
+    /// ```
+    pub fn replace_metadata(current: &UserMetadata, mut data: Map<String, Value>) -> UpdatedUser {
+        if let Ok(Value::Object(current)) = serde_json::to_value(current) {
+            for (key, _) in current {
+                data.entry(key).or_insert(Value::Null);
+            }
+        }
+
+        UpdatedUser::merge_metadata(data)
+    }
+}
+
+/// The result of [`AuthClient::update_user_with_session`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateUserResult {
+    pub user: User,
+    /// The session GoTrue returned if the update rotated the tokens, e.g. after a password
+    /// change. It has already replaced the stored session.
+    pub new_session: Option<Session>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct SendSMSOtpPayload<'a> {
     pub phone: &'a str,
+    #[serde(flatten)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) options: Option<LoginMobileOtpParams>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gotrue_meta_security: Option<GotrueMetaSecurity>,
 }
 
+/// Response to sending an OTP. GoTrue doesn't report the channel used.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OTPResponse {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The SMS provider's id of the message, for delivery reports. `None` for emails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub message_id: Option<String>,
+    /// Fields returned by the server that this crate doesn't model yet
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -280,6 +759,85 @@ pub enum VerifyOtpParams {
     TokenHash(VerifyTokenHashParams),
 }
 
+impl VerifyOtpParams {
+    /// Verify the `token` sent to `email`
+    ///
+    /// # Example
+    /// ```
+    /// let params = VerifyOtpParams::email(demo_email, "123456", EmailOtpType::Email);
+    /// ```
+    pub fn email(
+        email: impl Into<String>,
+        token: impl Into<String>,
+        otp_type: EmailOtpType,
+    ) -> VerifyOtpParams {
+        VerifyOtpParams::Email(VerifyEmailOtpParams {
+            email: email.into(),
+            token: token.into(),
+            otp_type: otp_type.into(),
+            options: None,
+        })
+    }
+
+    /// Verify the `token_hash` of an email link
+    ///
+    /// # Example
+    /// ```
+    /// let params = VerifyOtpParams::token_hash(token_hash, EmailOtpType::Recovery);
+    /// ```
+    pub fn token_hash(token_hash: impl Into<String>, otp_type: EmailOtpType) -> VerifyOtpParams {
+        VerifyOtpParams::TokenHash(VerifyTokenHashParams {
+            token_hash: token_hash.into(),
+            otp_type: otp_type.into(),
+        })
+    }
+
+    /// Verify the `token` sent by SMS to `phone` to sign in
+    ///
+    /// # Example
+    /// ```
+    /// let params = VerifyOtpParams::sms(demo_phone, "123456");
+    /// ```
+    pub fn sms(phone: impl Into<String>, token: impl Into<String>) -> VerifyOtpParams {
+        VerifyOtpParams::phone(phone, token, MobileOtpType::Sms)
+    }
+
+    /// Verify the `token` sent to `phone`
+    ///
+    /// # Example
+    /// ```
+    /// let params = VerifyOtpParams::phone(new_phone, "123456", MobileOtpType::PhoneChange);
+    /// ```
+    pub fn phone(
+        phone: impl Into<String>,
+        token: impl Into<String>,
+        otp_type: MobileOtpType,
+    ) -> VerifyOtpParams {
+        VerifyOtpParams::Mobile(VerifyMobileOtpParams {
+            phone: phone.into(),
+            token: token.into(),
+            otp_type: otp_type.into(),
+            options: None,
+        })
+    }
+
+    /// Where to send the user after they are confirmed. Token hashes don't take a redirect, so
+    /// they are returned unchanged.
+    pub fn redirect_to(mut self, redirect_to: impl Into<String>) -> VerifyOtpParams {
+        let options = Some(VerifyOtpOptions {
+            redirect_to: Some(redirect_to.into()),
+        });
+
+        match &mut self {
+            VerifyOtpParams::Mobile(params) => params.options = options,
+            VerifyOtpParams::Email(params) => params.options = options,
+            VerifyOtpParams::TokenHash(_) => {}
+        }
+
+        self
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VerifyMobileOtpParams {
     /// The user's phone number.
@@ -310,7 +868,7 @@ pub struct VerifyEmailOtpParams {
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VerifyTokenHashParams {
-    /// The user's phone number.
+    /// The `token_hash` of the email link
     pub token_hash: String,
     /// The user's verification type.
     #[serde(rename = "type")]
@@ -331,6 +889,46 @@ pub enum OtpType {
     Recovery,
 }
 
+/// The [`OtpType`]s sent by email, see [`VerifyOtpParams::email`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailOtpType {
+    Signup,
+    Invite,
+    Magiclink,
+    Recovery,
+    EmailChange,
+    Email,
+}
+
+impl From<EmailOtpType> for OtpType {
+    fn from(otp_type: EmailOtpType) -> Self {
+        match otp_type {
+            EmailOtpType::Signup => OtpType::Signup,
+            EmailOtpType::Invite => OtpType::Invite,
+            EmailOtpType::Magiclink => OtpType::Magiclink,
+            EmailOtpType::Recovery => OtpType::Recovery,
+            EmailOtpType::EmailChange => OtpType::EmailChange,
+            EmailOtpType::Email => OtpType::Email,
+        }
+    }
+}
+
+/// The [`OtpType`]s sent to a phone, see [`VerifyOtpParams::phone`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MobileOtpType {
+    Sms,
+    PhoneChange,
+}
+
+impl From<MobileOtpType> for OtpType {
+    fn from(otp_type: MobileOtpType) -> Self {
+        match otp_type {
+            MobileOtpType::Sms => OtpType::Sms,
+            MobileOtpType::PhoneChange => OtpType::PhoneChange,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VerifyOtpOptions {
     /// A URL to send the user to after they are confirmed.
@@ -344,19 +942,27 @@ pub(crate) struct LoginWithEmailOtpPayload<'a> {
     #[serde(flatten)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) options: Option<LoginEmailOtpParams>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gotrue_meta_security: Option<GotrueMetaSecurity>,
 }
 
 // align json field's name with https://github.com/supabase/auth/blob/1f7de6c65f31ef0bbb80899369989b13ab5a517f/openapi.yaml#L559
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LoginEmailOtpParams {
     /// Verification token received when the user completes the captcha on the site.
+    /// Sent as `gotrue_meta_security.captcha_token`.
+    #[serde(skip_serializing)]
     pub captcha_token: Option<String>,
     /// A custom data object to store the user's metadata. This maps to the `auth.users.raw_user_meta_data` column.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
     /// The redirect url embedded in the email link
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email_redirect_to: Option<String>,
-    /// If set to false, this method will not create a new user. Defaults to true.
+    /// If set to false, this method will not create a new user, so only existing users can sign
+    /// in. Defaults to true.
     #[serde(rename = "create_user")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub should_create_user: Option<bool>,
 }
 
@@ -364,16 +970,48 @@ pub struct LoginEmailOtpParams {
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LoginMobileOtpParams {
     /// Verification token received when the user completes the captcha on the site.
+    /// Sent as `gotrue_meta_security.captcha_token`.
+    #[serde(skip_serializing)]
     pub captcha_token: Option<String>,
     /// A custom data object to store the user's metadata. This maps to the `auth.users.raw_user_meta_data` column.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
-    /// The redirect url embedded in the email link
+    /// Channel the OTP is sent over, SMS unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub channel: Option<Channel>,
     /// If set to false, this method will not create a new user. Defaults to true.
     #[serde(rename = "create_user")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub should_create_user: Option<bool>,
 }
 
+/// Where [`AuthClient::send_otp`] sends a one-time password, with the options of that channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum OtpTarget {
+    Email {
+        email: String,
+        options: Option<LoginEmailOtpParams>,
+    },
+    Phone {
+        phone: String,
+        options: Option<LoginMobileOtpParams>,
+    },
+}
+
+/// A successful response to verifying an email change token
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum EmailChangeResponse {
+    /// Both addresses are confirmed
+    Session(Session),
+    /// Only one address is confirmed, GoTrue's message asks to confirm the other one
+    Pending {
+        #[serde(rename = "msg")]
+        _message: String,
+    },
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub(crate) struct RefreshSessionPayload<'a> {
     pub refresh_token: &'a str,
@@ -406,9 +1044,132 @@ pub struct ResendParams {
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct InviteParams {
     pub email: String,
+    /// Sent as the top-level `data` field, not nested under `options`, and stored as the invited
+    /// user's `raw_user_meta_data`. It is kept when the user accepts the invite.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Value>,
 }
 
+/// Attributes of a user created or updated through the admin API
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdminUserAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Confirms the user's email address without sending a confirmation email
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_confirm: Option<bool>,
+    /// Confirms the user's phone number without sending an SMS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_confirm: Option<bool>,
+    /// Custom data stored in the user's `raw_user_meta_data` column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<Value>,
+    /// Custom data stored in the user's `raw_app_meta_data` column. Users can't modify it themselves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// How long the user is banned for, as a Go duration string such as `24h`, or `none` to
+    /// lift a ban. Prefer [`AuthClient::admin_ban_user`] and [`AuthClient::admin_unban_user`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban_duration: Option<String>,
+}
+
+/// Pagination and filtering for [`AuthClient::admin_list_users`]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListUsersParams {
+    /// The page number, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Number of users per page. GoTrue defaults to 50.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+    /// Only return users whose email or name contains this value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// A page of users returned by [`AuthClient::admin_list_users`]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListUsersResponse {
+    pub users: Vec<User>,
+    #[serde(default)]
+    pub aud: String,
+}
+
+/// An SSO identity provider, managed with [`AuthClient::admin_create_sso_provider`] and the
+/// other `admin_*_sso_provider` methods
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SsoProvider {
+    pub id: Uuid,
+    /// An optional identifier set when the provider was created
+    #[serde(default)]
+    pub resource_id: Option<String>,
+    #[serde(default)]
+    pub saml: Option<SamlProvider>,
+    /// Email domains that sign in with this provider
+    #[serde(default)]
+    pub domains: Vec<SsoDomain>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// The SAML configuration of an [`SsoProvider`]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SamlProvider {
+    pub entity_id: String,
+    #[serde(default)]
+    pub metadata_url: Option<String>,
+    #[serde(default)]
+    pub metadata_xml: Option<String>,
+    #[serde(default)]
+    pub attribute_mapping: Option<Value>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SsoDomain {
+    pub domain: String,
+}
+
+/// A new SAML provider for [`AuthClient::admin_create_sso_provider`]. Set either
+/// `metadata_url` or `metadata_xml`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreateSsoProviderParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_xml: Option<String>,
+    /// Email domains that sign in with this provider
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// Maps SAML assertion attributes to user metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_mapping: Option<Value>,
+    /// An optional identifier to look the provider up by, instead of its id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CreateSsoProviderPayload {
+    /// GoTrue only supports SAML providers
+    #[serde(rename = "type")]
+    pub(crate) provider_type: &'static str,
+    #[serde(flatten)]
+    pub(crate) params: CreateSsoProviderParams,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SsoProvidersResponse {
+    pub(crate) items: Vec<SsoProvider>,
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DesktopResendOptions {
     pub email_redirect_to: Option<String>,
@@ -430,6 +1191,7 @@ pub struct MobileResendOptions {
     captcha_token: Option<String>,
 }
 
+/// Channel a phone OTP is sent over
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Channel {
@@ -455,9 +1217,23 @@ pub struct AuthServerHealth {
     /// Name of the service
     pub name: String,
     /// Description of the service
+    #[serde(default)]
     pub description: String,
 }
 
+impl AuthServerHealth {
+    /// Returns true if the server identifies itself as GoTrue
+    pub fn is_gotrue(&self) -> bool {
+        self.name.eq_ignore_ascii_case("gotrue")
+    }
+
+    /// The server version as a semantic version, e.g. `2.170.0` for `v2.170.0`.
+    /// Returns `None` if the version isn't a valid semantic version.
+    pub fn parsed_version(&self) -> Option<semver::Version> {
+        semver::Version::parse(self.version.trim_start_matches('v')).ok()
+    }
+}
+
 /// Settings of the Auth Server
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AuthServerSettings {
@@ -467,6 +1243,13 @@ pub struct AuthServerSettings {
     pub phone_autoconfirm: bool,
     pub sms_provider: String,
     pub saml_enabled: bool,
+    /// Minimum password length, if the server reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_min_length: Option<u32>,
+    /// Character sets a password needs one character of each of, separated by `:`, if the
+    /// server reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_required_characters: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -490,6 +1273,8 @@ pub struct External {
     pub spotify: bool,
     pub slack: bool,
     pub slack_oidc: bool,
+    #[serde(default)]
+    pub snapchat: bool,
     pub workos: bool,
     pub twitch: bool,
     pub twitter: bool,
@@ -498,6 +1283,58 @@ pub struct External {
     pub zoom: bool,
 }
 
+/// The server's password requirements, see [`AuthClient::password_policy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    /// Minimum length in bytes, as GoTrue counts it
+    pub min_length: u32,
+    /// Sets of characters a password needs at least one character of each of
+    pub required_characters: Vec<String>,
+}
+
+impl Default for PasswordPolicy {
+    /// GoTrue's defaults: at least 6 characters, no required characters
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            required_characters: Vec::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Check `password` against the policy, returning a message for each requirement it misses
+    ///
+    /// # Example
+    /// ```
+    /// if let Err(problems) = policy.validate(&password) {
+    ///     show_hints(&problems);
+    /// }
+    /// ```
+    pub fn validate(&self, password: &str) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if password.len() < self.min_length as usize {
+            problems.push(format!(
+                "Password should be at least {} characters",
+                self.min_length
+            ));
+        }
+
+        for set in &self.required_characters {
+            if !password.chars().any(|c| set.contains(c)) {
+                problems.push(format!("Password should contain at least one of: {}", set));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 /// Currently enabled OAuth providers.
@@ -525,6 +1362,7 @@ pub enum Provider {
     Notion,
     Slack,
     SlackOidc,
+    Snapchat,
     Spotify,
     Twitch,
     Twitter,
@@ -552,6 +1390,7 @@ impl Display for Provider {
             Provider::Notion => write!(f, "notion"),
             Provider::Slack => write!(f, "slack"),
             Provider::SlackOidc => write!(f, "slack_oidc"),
+            Provider::Snapchat => write!(f, "snapchat"),
             Provider::Spotify => write!(f, "spotify"),
             Provider::Twitch => write!(f, "twitch"),
             Provider::Twitter => write!(f, "twitter"),
@@ -561,6 +1400,40 @@ impl Display for Provider {
     }
 }
 
+impl FromStr for Provider {
+    type Err = Error;
+
+    /// Parse a provider from its GoTrue slug, e.g. `linkedin_oidc`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "apple" => Ok(Provider::Apple),
+            "azure" => Ok(Provider::Azure),
+            "bitbucket" => Ok(Provider::Bitbucket),
+            "discord" => Ok(Provider::Discord),
+            "facebook" => Ok(Provider::Facebook),
+            "figma" => Ok(Provider::Figma),
+            "fly" => Ok(Provider::Fly),
+            "github" => Ok(Provider::Github),
+            "gitlab" => Ok(Provider::Gitlab),
+            "google" => Ok(Provider::Google),
+            "kakao" => Ok(Provider::Kakao),
+            "keycloak" => Ok(Provider::Keycloak),
+            "linkedin" => Ok(Provider::Linkedin),
+            "linkedin_oidc" => Ok(Provider::LinkedinOidc),
+            "notion" => Ok(Provider::Notion),
+            "slack" => Ok(Provider::Slack),
+            "slack_oidc" => Ok(Provider::SlackOidc),
+            "snapchat" => Ok(Provider::Snapchat),
+            "spotify" => Ok(Provider::Spotify),
+            "twitch" => Ok(Provider::Twitch),
+            "twitter" => Ok(Provider::Twitter),
+            "workos" => Ok(Provider::Workos),
+            "zoom" => Ok(Provider::Zoom),
+            _ => Err(Error::UnknownProvider(s.to_owned())),
+        }
+    }
+}
+
 /// Represents the scope of the logout operation
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -581,6 +1454,52 @@ pub struct LoginWithSSO {
     pub domain: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<SSOLoginOptions>,
+    /// Where to send the user after they signed in, e.g. the page they started on. Sent to the
+    /// identity provider as the SAML relay state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+    /// Verification token received when the user completes the captcha on the site.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captcha_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct LoginWithSSOPayload<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) provider_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) domain: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) redirect_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gotrue_meta_security: Option<GotrueMetaSecurity>,
+}
+
+impl<'a> From<&'a LoginWithSSO> for LoginWithSSOPayload<'a> {
+    /// GoTrue reads `redirect_to` at the top level and the captcha token from
+    /// `gotrue_meta_security`, the values in `options` are used if they aren't set
+    fn from(params: &'a LoginWithSSO) -> Self {
+        let options = params.options.as_ref();
+
+        let redirect_to = params
+            .redirect_to
+            .as_deref()
+            .or(options.and_then(|o| o.redirect_to.as_deref()));
+
+        let captcha_token = params
+            .captcha_token
+            .as_ref()
+            .or(options.and_then(|o| o.captcha_token.as_ref()));
+
+        LoginWithSSOPayload {
+            provider_id: params.provider_id.as_deref(),
+            domain: params.domain.as_deref(),
+            redirect_to,
+            gotrue_meta_security: captcha_token.map(|token| GotrueMetaSecurity {
+                captcha_token: Some(token.clone()),
+            }),
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -655,14 +1574,68 @@ pub struct Headers {
 }
 
 // Implement custom Debug to avoid exposing sensitive information
+#[cfg(feature = "async")]
 impl fmt::Debug for AuthClient {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only report whether a session exists, its tokens are as sensitive as the keys
+        let has_session = self.session.borrow().is_some();
+
         f.debug_struct("AuthClient")
             .field("project_url", &self.project_url())
-            .field("api_key", &"[REDACTED]")
-            .field("jwt_secret", &"[REDACTED]")
+            .field("auth_path", &self.auth_path)
+            .field("api_key", &"***")
+            .field("jwt_secret", &"***")
+            .field("has_session", &has_session)
+            .field("persist_session", &self.persist_session)
+            .field("request_timeout", &self.request_timeout)
+            .field("refresh_margin", &self.refresh_margin)
+            .field("refresh_backoff", &self.refresh_backoff)
+            .field("retry_on_401", &self.retry_on_401)
             .finish()
     }
 }
 
+// Implement custom Debug to avoid exposing sensitive information
+#[cfg(feature = "async")]
+impl fmt::Debug for AuthClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthClientBuilder")
+            .field("project_url", &self.project_url)
+            .field("api_key", &"***")
+            .field("jwt_secret", &"***")
+            .field("http_client", &self.http_client)
+            .field("refresh_margin", &self.refresh_margin)
+            .field("settings_ttl", &self.settings_ttl)
+            .field("auth_path", &self.auth_path)
+            .field("clock", &self.clock)
+            .field("persist_session", &self.persist_session)
+            .field("refresh_backoff", &self.refresh_backoff)
+            .field("jitter_seed", &self.jitter_seed)
+            .field("retry_on_401", &self.retry_on_401)
+            .finish()
+    }
+}
+
+/// Default path GoTrue is served under, see [`AuthClientBuilder::auth_path`]
 pub const AUTH_V1: &str = "/auth/v1";
+
+/// Default margin before expiry at which the stored session is refreshed
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Cookie holding the access token, see [`AuthClient::session_from_cookies`]
+pub const ACCESS_TOKEN_COOKIE: &str = "sb-access-token";
+
+/// Cookie holding the refresh token, see [`AuthClient::session_from_cookies`]
+pub const REFRESH_TOKEN_COOKIE: &str = "sb-refresh-token";
+
+/// GoTrue's default minimum password length
+pub const DEFAULT_PASSWORD_MIN_LENGTH: u32 = 6;
+
+/// Default first delay before [`AuthClient::start_auto_refresh`] retries a failed refresh
+pub const DEFAULT_REFRESH_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Default longest delay between retries of [`AuthClient::start_auto_refresh`]
+pub const DEFAULT_MAX_REFRESH_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Default time for which [`AuthClient::get_settings`] reuses the last fetched settings
+pub const DEFAULT_SETTINGS_TTL: Duration = Duration::from_secs(5 * 60);
@@ -0,0 +1,499 @@
+/*!
+Logic shared by the async and the blocking client: everything that doesn't send a request, like
+decoding tokens, reading cookies, and building OAuth URLs.
+*/
+
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{decode, errors::ErrorKind, Algorithm, DecodingKey, Validation};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, COOKIE, RETRY_AFTER, SET_COOKIE},
+    StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::from_str;
+use uuid::Uuid;
+
+use crate::{
+    error::Error,
+    models::{
+        AuthServerSettings, Claims, JwtValidation, LoginWithOAuthOptions, OAuthResponse,
+        OAuthUrlBuilder, PasswordPolicy, Provider, Session, User, ACCESS_TOKEN_COOKIE, AUTH_V1,
+        DEFAULT_PASSWORD_MIN_LENGTH, REFRESH_TOKEN_COOKIE,
+    },
+};
+
+impl OAuthUrlBuilder {
+    /// Start building the authorize URL of `provider` for the project at `project_url`
+    pub fn new(project_url: impl Into<String>, provider: Provider) -> Self {
+        OAuthUrlBuilder {
+            project_url: project_url.into().trim_end_matches('/').to_owned(),
+            auth_path: AUTH_V1.to_string(),
+            provider,
+            redirect_to: None,
+            scopes: None,
+            query_params: Vec::new(),
+            state: None,
+        }
+    }
+
+    /// The path GoTrue is served under, see [`AuthClientBuilder::auth_path`](crate::models::AuthClientBuilder::auth_path)
+    pub fn auth_path(mut self, path: impl Into<String>) -> Self {
+        self.auth_path = path.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// A URL to send the user to after they are signed in
+    pub fn redirect_to(mut self, redirect_to: impl Into<String>) -> Self {
+        self.redirect_to = Some(redirect_to.into());
+        self
+    }
+
+    /// A space-separated list of scopes to request from the provider
+    pub fn scopes(mut self, scopes: impl Into<String>) -> Self {
+        self.scopes = Some(scopes.into());
+        self
+    }
+
+    /// Use the PKCE flow with the given S256 code challenge. Exchange the code GoTrue redirects
+    /// back with for a session with [`AuthClient::exchange_code_for_session`](crate::models::AuthClient::exchange_code_for_session).
+    pub fn pkce_challenge(self, code_challenge: impl Into<String>) -> Self {
+        self.query_param("code_challenge", code_challenge)
+            .query_param("code_challenge_method", "s256")
+    }
+
+    /// The `state` to send, a random one is generated if not set
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Add any other query parameter to the authorize URL
+    pub fn query_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query_params.push((key.into(), value.into()));
+        self
+    }
+
+    /// Build the authorize URL. Returns [`Error::ParseUrlError`] if the project URL is invalid.
+    pub fn build(self) -> Result<OAuthResponse, Error> {
+        // Generate a state for CSRF protection unless the caller supplied one
+        let state = self
+            .state
+            .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+        let mut query_params = vec![("provider".to_string(), self.provider.to_string())];
+
+        if let Some(redirect) = self.redirect_to {
+            query_params.push(("redirect_to".to_string(), redirect));
+        }
+
+        if let Some(scopes) = self.scopes {
+            query_params.push(("scopes".to_string(), scopes));
+        }
+
+        query_params.extend(self.query_params);
+        query_params.push(("state".to_string(), state.clone()));
+
+        let url = Url::parse_with_params(
+            &format!("{}{}/authorize", self.project_url, self.auth_path),
+            query_params,
+        )
+        .map_err(|_| Error::ParseUrlError)?;
+
+        Ok(OAuthResponse {
+            url,
+            provider: self.provider,
+            state,
+        })
+    }
+}
+
+/// The authorize URL of `provider` for the project, with the `options` of `login_with_oauth`
+pub(crate) fn oauth_url(
+    project_url: &str,
+    auth_path: &str,
+    provider: Provider,
+    options: Option<LoginWithOAuthOptions>,
+) -> Result<OAuthResponse, Error> {
+    let mut builder = OAuthUrlBuilder::new(project_url, provider).auth_path(auth_path);
+
+    if let Some(o) = options {
+        if let Some(redirect) = o.redirect_to {
+            builder = builder.redirect_to(redirect);
+        }
+
+        let scopes = o
+            .scopes_str
+            .into_iter()
+            .chain(o.scopes.unwrap_or_default())
+            .filter(|scope| !scope.is_empty())
+            .collect::<Vec<_>>();
+
+        if !scopes.is_empty() {
+            builder = builder.scopes(scopes.join(" "));
+        }
+
+        for (key, value) in o.query_params.unwrap_or_default() {
+            // Honor a caller supplied state
+            if key == "state" {
+                builder = builder.state(value);
+            } else {
+                builder = builder.query_param(key, value);
+            }
+        }
+    }
+
+    builder.build()
+}
+
+/// Check the `state` of an OAuth callback URL, read from the query string or, if the query
+/// doesn't contain one, from the fragment
+pub(crate) fn verify_oauth_state(callback_url: &str, expected_state: &str) -> Result<(), Error> {
+    let url = Url::parse(callback_url).map_err(|_| Error::ParseUrlError)?;
+
+    let from_query = url
+        .query_pairs()
+        .find(|(k, _)| k == "state")
+        .map(|(_, v)| v.into_owned());
+
+    let state = from_query.or_else(|| {
+        // Implicit flow callbacks carry their params in the fragment
+        let mut fragment_url = url.clone();
+        fragment_url.set_query(url.fragment());
+
+        fragment_url
+            .query_pairs()
+            .find(|(k, _)| k == "state")
+            .map(|(_, v)| v.into_owned())
+    });
+
+    match state {
+        Some(state) if state == expected_state => Ok(()),
+        _ => Err(Error::StateMismatch),
+    }
+}
+
+/// The `code` of a PKCE callback URL, or the error GoTrue put in the URL instead
+pub(crate) fn code_from_callback_url(callback_url: &str) -> Result<String, Error> {
+    let url = Url::parse(callback_url).map_err(|_| Error::ParseUrlError)?;
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+
+    if let Some(code) = param("code") {
+        return Ok(code);
+    }
+
+    match param("error_description").or_else(|| param("error")) {
+        // The redirect carries no status of its own
+        Some(message) => Err(Error::AuthError {
+            status: StatusCode::BAD_REQUEST,
+            code: param("error_code"),
+            message,
+        }),
+        None => Err(Error::ParseUrlError),
+    }
+}
+
+/// Verify a JWT signed with `jwt_secret` against `validation` and return its claims
+pub(crate) fn decode_verified<T: DeserializeOwned>(
+    jwt_secret: &str,
+    token: &str,
+    validation: &JwtValidation,
+) -> Result<T, Error> {
+    let mut jwt_validation = Validation::new(Algorithm::HS256);
+    jwt_validation.leeway = validation.leeway.as_secs();
+
+    match &validation.expected_audience {
+        Some(audience) => {
+            jwt_validation.set_audience(&[audience]);
+            jwt_validation
+                .required_spec_claims
+                .insert("aud".to_string());
+        }
+        None => jwt_validation.validate_aud = false,
+    }
+
+    if let Some(issuer) = &validation.expected_issuer {
+        jwt_validation.set_issuer(&[issuer]);
+        jwt_validation
+            .required_spec_claims
+            .insert("iss".to_string());
+    }
+
+    let token_data = decode::<T>(
+        token,
+        &DecodingKey::from_secret(jwt_secret.as_bytes()),
+        &jwt_validation,
+    )
+    .map_err(|error| match error.kind() {
+        ErrorKind::InvalidAudience => Error::InvalidAudience,
+        ErrorKind::InvalidIssuer => Error::InvalidIssuer,
+        ErrorKind::MissingRequiredClaim(claim) if claim == "aud" => Error::InvalidAudience,
+        ErrorKind::MissingRequiredClaim(claim) if claim == "iss" => Error::InvalidIssuer,
+        _ => Error::WrongToken,
+    })?;
+
+    Ok(token_data.claims)
+}
+
+/// Read the claims of a JWT without verifying its signature or expiry
+pub(crate) fn decode_jwt_unverified(token: &str) -> Result<Claims, Error> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.insecure_disable_signature_validation();
+    validation.validate_aud = false;
+    validation.validate_exp = false;
+
+    let token_data = decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map_err(|_| Error::WrongToken)?;
+
+    Ok(token_data.claims)
+}
+
+/// Build the session in the `sb-access-token` and `sb-refresh-token` cookies, verifying the
+/// access token with `jwt_secret`. `None` if either cookie is missing.
+pub(crate) fn session_from_cookies(
+    cookies: &HeaderMap,
+    jwt_secret: &str,
+    now: u64,
+) -> Result<Option<Session>, Error> {
+    let mut access_token = None;
+    let mut refresh_token = None;
+
+    let pairs = cookies
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='));
+
+    for (name, value) in pairs {
+        match name {
+            ACCESS_TOKEN_COOKIE => access_token = Some(value),
+            REFRESH_TOKEN_COOKIE => refresh_token = Some(value),
+            _ => {}
+        }
+    }
+
+    let (Some(access_token), Some(refresh_token)) = (access_token, refresh_token) else {
+        return Ok(None);
+    };
+
+    let claims: Claims = decode_verified(jwt_secret, access_token, &JwtValidation::default())?;
+    let expires_at = claims.exp;
+
+    Ok(Some(Session {
+        access_token: access_token.to_string(),
+        token_type: "bearer".to_string(),
+        expires_in: expires_at.saturating_sub(now) as i64,
+        expires_at,
+        refresh_token: refresh_token.to_string(),
+        user: user_from_claims(claims)?,
+        ..Default::default()
+    }))
+}
+
+/// `Set-Cookie` headers storing `session`, read back by [`session_from_cookies`]
+pub(crate) fn session_to_set_cookie_headers(
+    session: &Session,
+    now: u64,
+) -> Vec<(HeaderName, HeaderValue)> {
+    const REFRESH_TOKEN_MAX_AGE: u64 = 400 * 24 * 60 * 60;
+
+    let access_token_max_age = session.expires_at.saturating_sub(now);
+
+    [
+        (
+            ACCESS_TOKEN_COOKIE,
+            &session.access_token,
+            access_token_max_age,
+        ),
+        (
+            REFRESH_TOKEN_COOKIE,
+            &session.refresh_token,
+            REFRESH_TOKEN_MAX_AGE,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value, max_age)| {
+        HeaderValue::from_str(&format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+            name, value, max_age
+        ))
+        .ok()
+    })
+    .map(|value| (SET_COOKIE, value))
+    .collect()
+}
+
+/// The password requirements in `settings`, GoTrue's defaults where they aren't reported
+pub(crate) fn password_policy(settings: &AuthServerSettings) -> PasswordPolicy {
+    PasswordPolicy {
+        min_length: settings
+            .password_min_length
+            .unwrap_or(DEFAULT_PASSWORD_MIN_LENGTH),
+        required_characters: settings
+            .password_required_characters
+            .as_deref()
+            .map(required_character_sets)
+            .unwrap_or_default(),
+    }
+}
+
+/// The refresh margin, capped to half of the session's lifetime
+pub(crate) fn effective_refresh_margin(refresh_margin: Duration, session: &Session) -> Duration {
+    let lifetime = Duration::from_secs(session.expires_in.max(0) as u64);
+
+    if refresh_margin < lifetime {
+        refresh_margin
+    } else {
+        lifetime / 2
+    }
+}
+
+/// Warn if `refresh_margin` isn't smaller than the lifetime of `session`, once per lifetime
+pub(crate) fn warn_on_long_refresh_margin(
+    refresh_margin: Duration,
+    warned_for: &AtomicI64,
+    session: &Session,
+) {
+    let lifetime = Duration::from_secs(session.expires_in.max(0) as u64);
+
+    // Sessions without a known lifetime, and lifetimes already warned about, are skipped
+    if session.expires_in > 0
+        && refresh_margin >= lifetime
+        && warned_for.swap(session.expires_in, Ordering::Relaxed) != session.expires_in
+    {
+        log::warn!(
+            "refresh margin of {:?} isn't smaller than the session lifetime of {:?}, \
+             refreshing after half of the lifetime instead",
+            refresh_margin,
+            lifetime
+        );
+    }
+}
+
+/// `now` as a unix timestamp
+pub(crate) fn unix_time(now: SystemTime) -> u64 {
+    now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Parse the body of a successful response, treating an empty body, e.g. of a 204, as the
+/// default value instead of invalid JSON
+pub(crate) fn parse_success_body<T: DeserializeOwned + Default>(body: &str) -> Result<T, Error> {
+    if body.trim().is_empty() {
+        return Ok(T::default());
+    }
+
+    Ok(from_str(body)?)
+}
+
+/// Whether GoTrue rejected a refresh token because it is unknown, revoked, or expired
+pub(crate) fn is_invalid_refresh_token(code: Option<&str>, message: &str) -> bool {
+    match code {
+        Some(code) => matches!(
+            code,
+            "refresh_token_not_found"
+                | "refresh_token_already_used"
+                | "session_not_found"
+                | "session_expired"
+        ),
+        // Older GoTrue versions don't send error codes
+        None => message.starts_with("Invalid Refresh Token"),
+    }
+}
+
+/// Name the `redirect_to` in a redirect related error, GoTrue's messages don't include it
+pub(crate) fn with_redirect_to(
+    message: String,
+    code: Option<&str>,
+    redirect_to: Option<&str>,
+) -> String {
+    let about_redirect = code.is_some_and(|code| code.contains("redirect"))
+        || message.to_lowercase().contains("redirect");
+
+    match redirect_to {
+        Some(redirect_to) if about_redirect => {
+            format!("{} (redirect_to: {})", message, redirect_to)
+        }
+        _ => message,
+    }
+}
+
+/// Split GoTrue's required password characters into their sets. Sets are separated by `:`,
+/// a `\:` is a literal colon.
+fn required_character_sets(value: &str) -> Vec<String> {
+    let mut sets = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&':') => {
+                current.push(':');
+                chars.next();
+            }
+            ':' => sets.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+
+    sets.push(current);
+    sets.retain(|set| !set.is_empty());
+    sets
+}
+
+/// The user as far as the claims of its access token tell
+pub(crate) fn user_from_claims(claims: Claims) -> Result<User, Error> {
+    Ok(User {
+        id: Uuid::parse_str(&claims.sub).map_err(|_| Error::WrongToken)?,
+        aud: claims.aud,
+        role: claims.role,
+        email: claims.email.unwrap_or_default(),
+        phone: claims.phone.unwrap_or_default(),
+        app_metadata: claims.app_metadata,
+        user_metadata: claims.user_metadata,
+        is_anonymous: claims.is_anonymous,
+        ..Default::default()
+    })
+}
+
+/// Parse a `Retry-After` header given in seconds. HTTP dates aren't supported.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Format a duration the way Go's `time.ParseDuration` expects it, e.g. `24h` or `1h30m`
+pub(crate) fn format_ban_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+
+    let mut formatted = String::new();
+
+    if hours > 0 {
+        formatted.push_str(&format!("{}h", hours));
+    }
+
+    if minutes > 0 {
+        formatted.push_str(&format!("{}m", minutes));
+    }
+
+    if seconds > 0 || formatted.is_empty() {
+        formatted.push_str(&format!("{}s", seconds));
+    }
+
+    formatted
+}
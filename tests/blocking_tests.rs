@@ -0,0 +1,261 @@
+#![cfg(feature = "blocking")]
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use wiremock::{
+    matchers::{header, method, path, path_regex, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use supabase_auth::{blocking::AuthClient, clock::MockClock, error::Error, models::Session};
+
+// The blocking client can't run on a runtime, so the mock server gets one of its own
+fn mock_server() -> (tokio::runtime::Runtime, MockServer) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start());
+
+    (runtime, server)
+}
+
+fn user_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "0190c3b5-7a4b-7c9e-9e6e-8c0f5b2a1d3e",
+        "aud": "authenticated",
+        "role": "authenticated",
+        "email": "demo@demo.com",
+        "phone": "",
+        "app_metadata": { "provider": "email", "providers": ["email"] },
+        "user_metadata": {},
+        "identities": [],
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+        "is_anonymous": false
+    })
+}
+
+fn session_json() -> serde_json::Value {
+    serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "user": user_json()
+    })
+}
+
+#[test]
+fn blocking_login_with_email_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "password"))
+            .and(header("apikey", "api_key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(session_json()))
+            .mount(&server),
+    );
+
+    let auth_client = AuthClient::new(server.uri(), "api_key", "jwt_secret").unwrap();
+
+    let session = auth_client
+        .login_with_email("demo@demo.com", "qwerty123")
+        .unwrap();
+
+    assert_eq!(session.user.email, "demo@demo.com");
+    assert!(auth_client.is_authenticated());
+}
+
+#[test]
+fn blocking_error_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "code": 400,
+                "error_code": "invalid_credentials",
+                "msg": "Invalid login credentials"
+            })))
+            .mount(&server),
+    );
+
+    let auth_client = AuthClient::new(server.uri(), "api_key", "jwt_secret").unwrap();
+
+    match auth_client.login_with_email("demo@demo.com", "wrong") {
+        Err(Error::AuthError { code, .. }) => {
+            assert_eq!(code.as_deref(), Some("invalid_credentials"))
+        }
+        other => panic!("Expected an AuthError, got {:?}", other),
+    }
+}
+
+#[test]
+fn blocking_builder_test() {
+    let auth_client = AuthClient::builder("https://example.supabase.co/", "api_key", "jwt_secret")
+        .auth_path("/custom/auth/")
+        .persist_session(false)
+        .build()
+        .unwrap();
+
+    assert_eq!(auth_client.project_url(), "https://example.supabase.co");
+    assert_eq!(auth_client.auth_path(), "/custom/auth");
+    assert!(!auth_client.is_authenticated());
+
+    let session: Session = serde_json::from_value(session_json()).unwrap();
+    auth_client.set_session_from_struct(session);
+
+    assert!(matches!(
+        auth_client.get_session(),
+        Err(Error::SessionNotPersisted)
+    ));
+
+    assert!(matches!(
+        AuthClient::new("not a url", "api_key", "jwt_secret"),
+        Err(Error::ParseUrlError)
+    ));
+}
+
+#[test]
+fn blocking_get_session_with_mock_clock_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "refresh_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(session_json()))
+            .expect(1)
+            .mount(&server),
+    );
+
+    let expires_at = 1_700_000_000;
+    let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(expires_at - 61));
+
+    let auth_client = AuthClient::builder(server.uri(), "api_key", "jwt_secret")
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+
+    let mut session = session_json();
+    session["expires_at"] = serde_json::json!(expires_at);
+    session["refresh_token"] = serde_json::json!("expiring_refresh_token");
+    auth_client.set_session_from_struct(serde_json::from_value(session).unwrap());
+
+    // One second outside of the default 60 second margin
+    let session = auth_client.get_session().unwrap();
+    assert_eq!(session.refresh_token, "expiring_refresh_token");
+    assert!(!auth_client.session_expired().unwrap());
+
+    // Exactly at the margin
+    clock.advance(Duration::from_secs(1));
+    let session = auth_client.get_session().unwrap();
+    assert_eq!(session.refresh_token, "refresh_token");
+}
+
+#[test]
+fn blocking_retry_on_401_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(async {
+        Mock::given(method("GET"))
+            .and(path("/auth/v1/user"))
+            .and(header("Authorization", "Bearer stale_access_token"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "code": 401,
+                "error_code": "bad_jwt",
+                "msg": "invalid JWT"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "refresh_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(session_json()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/auth/v1/user"))
+            .and(header("Authorization", "Bearer access_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(user_json()))
+            .expect(1)
+            .mount(&server)
+            .await;
+    });
+
+    let auth_client = AuthClient::builder(server.uri(), "api_key", "jwt_secret")
+        .retry_on_401(true)
+        .build()
+        .unwrap();
+
+    let mut session = session_json();
+    session["access_token"] = serde_json::json!("stale_access_token");
+    auth_client.set_session_from_struct(serde_json::from_value(session).unwrap());
+
+    let user = auth_client.get_user("stale_access_token").unwrap();
+
+    assert_eq!(user.email, "demo@demo.com");
+    assert_eq!(auth_client.session().unwrap().access_token, "access_token");
+}
+
+#[test]
+fn blocking_rate_limited_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/magiclink"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("Retry-After", "30")
+                    .set_body_json(serde_json::json!({
+                        "code": 429,
+                        "error_code": "over_email_send_rate_limit",
+                        "msg": "email rate limit exceeded"
+                    })),
+            )
+            .mount(&server),
+    );
+
+    let auth_client = AuthClient::new(server.uri(), "api_key", "jwt_secret").unwrap();
+
+    match auth_client.send_login_email_with_magic_link("demo@demo.com") {
+        Err(Error::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Some(Duration::from_secs(30)))
+        }
+        other => panic!("Expected RateLimited, got {:?}", other),
+    }
+}
+
+#[test]
+fn blocking_admin_delete_users_test() {
+    let (runtime, server) = mock_server();
+
+    runtime.block_on(
+        Mock::given(method("DELETE"))
+            .and(path_regex("^/auth/v1/admin/users/[^/]+$"))
+            .and(header("Authorization", "Bearer service_role_key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(5)
+            .mount(&server),
+    );
+
+    let auth_client = AuthClient::new(server.uri(), "api_key", "jwt_secret").unwrap();
+
+    let ids: Vec<String> = (0..5).map(|i| format!("user_{}", i)).collect();
+
+    let mut results = auth_client.admin_delete_users(&ids, 3, "service_role_key");
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    assert_eq!(
+        results.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+        ids.iter().collect::<Vec<_>>()
+    );
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+}
@@ -0,0 +1,644 @@
+use std::time::Duration;
+use supabase_auth::{
+    error::Error,
+    models::{
+        AdminUserAttributes, AuthServerHealth, AuthenticatorAssuranceLevel,
+        CreateSsoProviderParams, DesktopResendOptions, EmailOtpType, EmailSignUpConfirmation,
+        EmailSignUpResult, ListUsersParams, LoginAnonymouslyOptions, LoginEmailOtpParams,
+        LoginMobileOtpParams, LoginWithOAuthOptions, LoginWithPasswordOptions, MobileOtpType,
+        MobileResendOptions, OTPResponse, PasswordPolicy, Provider, ResetPasswordOptions,
+        SSOLoginOptions, Session, SignUpWithPasswordOptions, UpdatedUser, User, VerifyOtpOptions,
+        VerifyOtpParams,
+    },
+};
+
+fn user_json() -> serde_json::Value {
+    serde_json::json!({
+        "id": "0190c3b5-7a4b-7c9e-9e6e-8c0f5b2a1d3e",
+        "aud": "authenticated",
+        "role": "authenticated",
+        "email": "demo@demo.com",
+        "email_confirmed_at": "2024-01-01T00:00:00Z",
+        "phone": "",
+        "app_metadata": { "provider": "email", "providers": ["email"] },
+        "user_metadata": {},
+        "identities": [],
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+        "is_anonymous": false
+    })
+}
+
+#[test]
+fn user_with_unknown_fields_test() {
+    let mut json = user_json();
+    json["is_sso_user"] = serde_json::json!(false);
+    json["passkeys"] = serde_json::json!([{ "id": "passkey" }]);
+    json["app_metadata"]["stripe_customer_id"] = serde_json::json!("cus_123");
+
+    let user: User = serde_json::from_value(json).unwrap();
+
+    assert_eq!(user.email, "demo@demo.com");
+    assert_eq!(user.extra.get("is_sso_user").unwrap(), false);
+    assert!(user.extra.get("passkeys").unwrap().is_array());
+    assert_eq!(
+        user.app_metadata.custom.get("stripe_customer_id").unwrap(),
+        "cus_123"
+    );
+
+    // Unknown fields survive a round trip
+    let round_trip: User = serde_json::from_value(serde_json::to_value(&user).unwrap()).unwrap();
+    assert_eq!(round_trip, user);
+}
+
+#[test]
+fn session_with_unknown_fields_test() {
+    let json = serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "weak_password": null,
+        "user": user_json()
+    });
+
+    let session: Session = serde_json::from_value(json).unwrap();
+
+    assert_eq!(session.refresh_token, "refresh_token");
+    assert!(session.extra.contains_key("weak_password"));
+}
+
+#[test]
+fn user_accessors_test() {
+    let user: User = serde_json::from_value(user_json()).unwrap();
+
+    assert!(!user.is_anonymous());
+    assert_eq!(user.role(), Some("authenticated"));
+    assert!(user.has_confirmed_email());
+    assert_eq!(user.primary_provider().as_deref(), Some("email"));
+    assert_eq!(user.linked_providers(), vec!["email"]);
+
+    let mut json = user_json();
+    json["app_metadata"] = serde_json::json!({
+        "provider": "email",
+        "providers": ["email", "github"]
+    });
+
+    let linked: User = serde_json::from_value(json).unwrap();
+
+    assert_eq!(linked.primary_provider().as_deref(), Some("email"));
+    assert_eq!(linked.linked_providers(), vec!["email", "github"]);
+
+    let mut json = user_json();
+    json["role"] = serde_json::json!("");
+    json["app_metadata"] = serde_json::json!({});
+    json["is_anonymous"] = serde_json::json!(true);
+    json["email_confirmed_at"] = serde_json::Value::Null;
+
+    let anonymous: User = serde_json::from_value(json).unwrap();
+
+    assert!(anonymous.is_anonymous());
+    assert_eq!(anonymous.role(), None);
+    assert!(!anonymous.has_confirmed_email());
+    assert_eq!(anonymous.primary_provider(), None);
+    assert!(anonymous.linked_providers().is_empty());
+}
+
+#[test]
+fn auth_server_health_test() {
+    let health: AuthServerHealth = serde_json::from_value(serde_json::json!({
+        "version": "v2.170.0",
+        "name": "GoTrue",
+        "description": "GoTrue is a user registration and authentication API"
+    }))
+    .unwrap();
+
+    assert!(health.is_gotrue());
+    assert_eq!(
+        health.parsed_version(),
+        Some(semver::Version::new(2, 170, 0))
+    );
+    assert!(health.parsed_version().unwrap() >= semver::Version::new(2, 100, 0));
+
+    let unknown: AuthServerHealth = serde_json::from_value(serde_json::json!({
+        "version": "unknown",
+        "name": "auth"
+    }))
+    .unwrap();
+
+    assert!(!unknown.is_gotrue());
+    assert_eq!(unknown.parsed_version(), None);
+}
+
+#[test]
+fn login_email_otp_params_serialization_test() {
+    let allow_sign_up = LoginEmailOtpParams {
+        should_create_user: Some(true),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        serde_json::to_value(allow_sign_up).unwrap(),
+        serde_json::json!({ "create_user": true })
+    );
+
+    let existing_users_only = LoginEmailOtpParams {
+        should_create_user: Some(false),
+        data: Some(serde_json::json!({ "plan": "free" })),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        serde_json::to_value(existing_users_only).unwrap(),
+        serde_json::json!({ "create_user": false, "data": { "plan": "free" } })
+    );
+
+    assert_eq!(
+        serde_json::to_value(LoginEmailOtpParams::default()).unwrap(),
+        serde_json::json!({})
+    );
+}
+
+#[test]
+fn anonymous_sign_up_response_test() {
+    let json = serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "user": {
+            "id": "0190c3b5-7a4b-7c9e-9e6e-8c0f5b2a1d3e",
+            "aud": "authenticated",
+            "role": "authenticated",
+            "email": "",
+            "phone": "",
+            "last_sign_in_at": "2024-01-01T00:00:00Z",
+            "app_metadata": {},
+            "user_metadata": {},
+            "identities": [],
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "is_anonymous": true
+        }
+    });
+
+    let session: Session = serde_json::from_value(json.clone()).unwrap();
+
+    assert!(session.user.is_anonymous());
+    assert_eq!(session.is_new_user, None);
+
+    let mut json = json;
+    json["is_new_user"] = serde_json::json!(true);
+
+    let session: Session = serde_json::from_value(json).unwrap();
+
+    assert_eq!(session.is_new_user, Some(true));
+    assert!(!session.extra.contains_key("is_new_user"));
+}
+
+#[test]
+fn user_with_identities_test() {
+    let mut json = user_json();
+    json["identities"] = serde_json::json!([
+        {
+            "identity_id": "5f0e5b2a-6a4b-4c9e-9e6e-8c0f5b2a1d3e",
+            "id": "1234567",
+            "user_id": "0190c3b5-7a4b-7c9e-9e6e-8c0f5b2a1d3e",
+            "identity_data": {
+                "avatar_url": "https://avatars.githubusercontent.com/u/1234567?v=4",
+                "email": "demo@demo.com",
+                "email_verified": true,
+                "full_name": "Demo User",
+                "iss": "https://api.github.com",
+                "phone_verified": false,
+                "preferred_username": "demo",
+                "provider_id": "1234567",
+                "sub": "1234567",
+                "user_name": "demo"
+            },
+            "provider": "github",
+            "last_sign_in_at": "2024-01-01T00:00:00Z",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "email": "demo@demo.com"
+        },
+        {
+            "identity_id": "6f0e5b2a-6a4b-4c9e-9e6e-8c0f5b2a1d3e",
+            "id": "109876543210987654321",
+            "user_id": "0190c3b5-7a4b-7c9e-9e6e-8c0f5b2a1d3e",
+            "identity_data": {
+                "email": "demo@demo.com",
+                "email_verified": true,
+                "name": "Demo User",
+                "picture": "https://lh3.googleusercontent.com/a/demo",
+                "sub": "109876543210987654321"
+            },
+            "provider": "google",
+            "last_sign_in_at": null,
+            "created_at": "2024-01-02T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z"
+        }
+    ]);
+
+    let user: User = serde_json::from_value(json).unwrap();
+
+    assert_eq!(user.identities.len(), 2);
+
+    let github = &user.identities[0];
+    assert_eq!(github.provider, "github");
+    assert_eq!(
+        github.identity_data.preferred_username.as_deref(),
+        Some("demo")
+    );
+    assert_eq!(
+        github.identity_data.extra.get("user_name"),
+        Some(&serde_json::json!("demo"))
+    );
+
+    let google = &user.identities[1];
+    assert_eq!(google.provider, "google");
+    assert_eq!(google.identity_data.name.as_deref(), Some("Demo User"));
+    assert!(!google.identity_data.phone_verified);
+    assert!(google.last_sign_in_at.is_none());
+}
+
+#[test]
+fn session_expires_within_at_test() {
+    let session = Session {
+        expires_at: 1_700_000_000,
+        ..Default::default()
+    };
+
+    let at = |secs| std::time::UNIX_EPOCH + Duration::from_secs(secs);
+
+    assert!(!session.expires_within_at(Duration::from_secs(60), at(1_699_999_939)));
+    assert!(session.expires_within_at(Duration::from_secs(60), at(1_699_999_940)));
+    assert!(session.expires_within_at(Duration::ZERO, at(1_700_000_000)));
+}
+
+#[test]
+fn provider_round_trip_test() {
+    let providers = [
+        (Provider::Apple, "apple"),
+        (Provider::Azure, "azure"),
+        (Provider::Bitbucket, "bitbucket"),
+        (Provider::Discord, "discord"),
+        (Provider::Facebook, "facebook"),
+        (Provider::Figma, "figma"),
+        (Provider::Fly, "fly"),
+        (Provider::Github, "github"),
+        (Provider::Gitlab, "gitlab"),
+        (Provider::Google, "google"),
+        (Provider::Kakao, "kakao"),
+        (Provider::Keycloak, "keycloak"),
+        (Provider::Linkedin, "linkedin"),
+        (Provider::LinkedinOidc, "linkedin_oidc"),
+        (Provider::Notion, "notion"),
+        (Provider::Slack, "slack"),
+        (Provider::SlackOidc, "slack_oidc"),
+        (Provider::Snapchat, "snapchat"),
+        (Provider::Spotify, "spotify"),
+        (Provider::Twitch, "twitch"),
+        (Provider::Twitter, "twitter"),
+        (Provider::Workos, "workos"),
+        (Provider::Zoom, "zoom"),
+    ];
+
+    for (provider, slug) in providers {
+        assert_eq!(provider.to_string(), slug);
+        assert_eq!(slug.parse::<Provider>().unwrap(), provider);
+        assert_eq!(serde_json::to_value(&provider).unwrap(), slug);
+    }
+
+    assert!(matches!(
+        "myspace".parse::<Provider>(),
+        Err(Error::UnknownProvider(slug)) if slug == "myspace"
+    ));
+}
+
+#[test]
+fn otp_response_test() {
+    let sms: OTPResponse =
+        serde_json::from_value(serde_json::json!({ "message_id": "SM1234567890" })).unwrap();
+
+    assert_eq!(sms.message_id.as_deref(), Some("SM1234567890"));
+
+    let email: OTPResponse = serde_json::from_value(serde_json::json!({})).unwrap();
+
+    assert_eq!(email.message_id, None);
+    assert!(email.extra.is_empty());
+}
+
+#[test]
+fn email_sign_up_result_accessors_test() {
+    let session: Session = serde_json::from_value(serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "user": user_json()
+    }))
+    .unwrap();
+
+    let result = EmailSignUpResult::SessionResult(session.clone());
+    assert_eq!(result.session(), Some(&session));
+    assert!(result.confirmation().is_none());
+    assert!(!result.is_confirmation_required());
+
+    let confirmation = EmailSignUpConfirmation {
+        email: Some("demo@demo.com".to_string()),
+        ..Default::default()
+    };
+
+    let result = EmailSignUpResult::ConfirmationResult(confirmation.clone());
+    assert!(result.session().is_none());
+    assert_eq!(result.confirmation(), Some(&confirmation));
+    assert!(result.is_confirmation_required());
+}
+
+#[test]
+fn session_authenticator_assurance_level_test() {
+    let session_with = |claims: serde_json::Value, secret: &[u8]| Session {
+        access_token: jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(secret),
+        )
+        .unwrap(),
+        ..Default::default()
+    };
+
+    let key = jsonwebtoken::DecodingKey::from_secret(b"jwt_secret");
+
+    assert_eq!(
+        session_with(
+            serde_json::json!({ "sub": "user", "aal": "aal1" }),
+            b"jwt_secret"
+        )
+        .authenticator_assurance_level(&key)
+        .unwrap(),
+        Some(AuthenticatorAssuranceLevel::Aal1)
+    );
+    assert_eq!(
+        session_with(
+            serde_json::json!({ "sub": "user", "aal": "aal2" }),
+            b"jwt_secret"
+        )
+        .authenticator_assurance_level(&key)
+        .unwrap(),
+        Some(AuthenticatorAssuranceLevel::Aal2)
+    );
+    assert_eq!(
+        session_with(serde_json::json!({ "sub": "user" }), b"jwt_secret")
+            .authenticator_assurance_level(&key)
+            .unwrap(),
+        None
+    );
+
+    // Forged or missing tokens are rejected instead of trusting their claims
+    assert!(matches!(
+        session_with(
+            serde_json::json!({ "sub": "user", "aal": "aal2" }),
+            b"forged_secret"
+        )
+        .authenticator_assurance_level(&key),
+        Err(Error::WrongToken)
+    ));
+    assert!(matches!(
+        Session::default().authenticator_assurance_level(&key),
+        Err(Error::WrongToken)
+    ));
+}
+
+#[test]
+fn options_default_test() {
+    // Every options struct can be built with only the fields that matter
+    let data = Some(serde_json::json!({ "display_name": "Demo" }));
+
+    let sign_up = SignUpWithPasswordOptions {
+        data: data.clone(),
+        ..Default::default()
+    };
+    assert_eq!(sign_up.captcha_token, None);
+
+    let _ = LoginAnonymouslyOptions {
+        data: data.clone(),
+        ..Default::default()
+    };
+    let _ = LoginEmailOtpParams {
+        data: data.clone(),
+        ..Default::default()
+    };
+    let _ = LoginMobileOtpParams {
+        data,
+        ..Default::default()
+    };
+    let _ = LoginWithOAuthOptions {
+        redirect_to: Some("https://example.com".to_string()),
+        ..Default::default()
+    };
+    let _ = ResetPasswordOptions {
+        email_redirect_to: Some("https://example.com".to_string()),
+        ..Default::default()
+    };
+    let _ = AdminUserAttributes {
+        email_confirm: Some(true),
+        ..Default::default()
+    };
+    let _ = ListUsersParams {
+        page: Some(2),
+        ..Default::default()
+    };
+    let _ = CreateSsoProviderParams {
+        domains: vec!["example.com".to_string()],
+        ..Default::default()
+    };
+    let _ = LoginWithPasswordOptions::default();
+    let _ = VerifyOtpOptions::default();
+    let _ = SSOLoginOptions::default();
+    let _ = DesktopResendOptions::default();
+    let _ = MobileResendOptions::default();
+}
+
+#[test]
+fn typed_metadata_test() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Profile {
+        display_name: String,
+        team: Option<String>,
+    }
+
+    let profile = Profile {
+        display_name: "Demo".to_string(),
+        team: Some("engineering".to_string()),
+    };
+
+    let updated_user = UpdatedUser::with_metadata(&profile).unwrap();
+    assert_eq!(
+        updated_user.data,
+        Some(serde_json::json!({ "display_name": "Demo", "team": "engineering" }))
+    );
+    assert_eq!(updated_user.email, None);
+
+    let mut json = user_json();
+    json["user_metadata"] = updated_user.data.unwrap();
+
+    let user: User = serde_json::from_value(json).unwrap();
+    assert_eq!(user.metadata::<Profile>().unwrap(), profile);
+
+    // Metadata that doesn't fit the type is a deserialization error
+    let user: User = serde_json::from_value(user_json()).unwrap();
+    assert!(matches!(
+        user.metadata::<Profile>(),
+        Err(Error::Deserialization(_))
+    ));
+}
+
+#[test]
+fn update_metadata_semantics_test() {
+    let mut json = user_json();
+    json["user_metadata"] = serde_json::json!({
+        "avatar_url": "https://example.com/demo.png",
+        "theme": "light",
+        "beta_tester": true
+    });
+    let user: User = serde_json::from_value(json).unwrap();
+
+    // Only the delta is sent, null removes a key
+    let delta = serde_json::json!({ "theme": "dark", "beta_tester": null });
+    let updated_user = UpdatedUser::merge_metadata(delta.as_object().unwrap().clone());
+    assert_eq!(
+        serde_json::to_value(&updated_user).unwrap(),
+        serde_json::json!({
+            "email": null,
+            "password": null,
+            "data": { "theme": "dark", "beta_tester": null }
+        })
+    );
+
+    // The full object is sent, with the keys it drops set to null
+    let data = serde_json::json!({ "theme": "dark", "locale": "en" });
+    let updated_user =
+        UpdatedUser::replace_metadata(&user.user_metadata, data.as_object().unwrap().clone());
+    assert_eq!(
+        updated_user.data,
+        Some(serde_json::json!({
+            "theme": "dark",
+            "locale": "en",
+            "avatar_url": null,
+            "beta_tester": null
+        }))
+    );
+}
+
+#[test]
+fn oauth_session_provider_tokens_test() {
+    let mut user = user_json();
+    user["app_metadata"] = serde_json::json!({ "provider": "github", "providers": ["github"] });
+
+    let json = serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "provider_token": "gho_provider_token",
+        "provider_refresh_token": "ghr_provider_refresh_token",
+        "user": user
+    });
+
+    let session: Session = serde_json::from_value(json).unwrap();
+
+    assert_eq!(
+        session.provider_token.as_deref(),
+        Some("gho_provider_token")
+    );
+    assert_eq!(
+        session.provider_refresh_token.as_deref(),
+        Some("ghr_provider_refresh_token")
+    );
+    assert!(session.extra.is_empty());
+    assert_eq!(session.user.primary_provider().as_deref(), Some("github"));
+
+    // Password logins don't have provider tokens
+    let session: Session = serde_json::from_value(serde_json::json!({
+        "access_token": "access_token",
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "expires_at": 4102444800u64,
+        "refresh_token": "refresh_token",
+        "user": user_json()
+    }))
+    .unwrap();
+
+    assert_eq!(session.provider_token, None);
+    assert_eq!(session.provider_refresh_token, None);
+}
+
+#[test]
+fn verify_otp_params_constructors_test() {
+    let params = VerifyOtpParams::email("demo@demo.com", "123456", EmailOtpType::Signup)
+        .redirect_to("https://example.com/welcome");
+    assert_eq!(
+        serde_json::to_value(&params).unwrap(),
+        serde_json::json!({
+            "email": "demo@demo.com",
+            "token": "123456",
+            "type": "signup",
+            "options": { "redirect_to": "https://example.com/welcome" }
+        })
+    );
+
+    let params = VerifyOtpParams::token_hash("token_hash", EmailOtpType::Recovery)
+        .redirect_to("https://example.com/reset");
+    assert_eq!(
+        serde_json::to_value(&params).unwrap(),
+        serde_json::json!({ "token_hash": "token_hash", "type": "recovery" })
+    );
+
+    let params = VerifyOtpParams::sms("+15555550100", "123456");
+    assert_eq!(
+        serde_json::to_value(&params).unwrap(),
+        serde_json::json!({ "phone": "+15555550100", "token": "123456", "type": "sms" })
+    );
+
+    let params = VerifyOtpParams::phone("+15555550100", "123456", MobileOtpType::PhoneChange);
+    assert_eq!(
+        serde_json::to_value(&params).unwrap(),
+        serde_json::json!({ "phone": "+15555550100", "token": "123456", "type": "phone_change" })
+    );
+
+    let params = VerifyOtpParams::email("demo@demo.com", "123456", EmailOtpType::EmailChange);
+    assert_eq!(
+        serde_json::to_value(&params).unwrap()["type"],
+        "email_change"
+    );
+}
+
+#[test]
+fn password_policy_validate_test() {
+    let policy = PasswordPolicy::default();
+    assert_eq!(policy.min_length, 6);
+    assert!(policy.validate("qwerty").is_ok());
+    assert_eq!(
+        policy.validate("qwert"),
+        Err(vec!["Password should be at least 6 characters".to_string()])
+    );
+
+    let policy = PasswordPolicy {
+        min_length: 8,
+        required_characters: vec!["abc".to_string(), "0123456789".to_string()],
+    };
+    assert!(policy.validate("abcdefg1").is_ok());
+    assert_eq!(
+        policy.validate("xyz"),
+        Err(vec![
+            "Password should be at least 8 characters".to_string(),
+            "Password should contain at least one of: abc".to_string(),
+            "Password should contain at least one of: 0123456789".to_string(),
+        ])
+    );
+}